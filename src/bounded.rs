@@ -0,0 +1,149 @@
+use crate::{lock, ScoredSortedSet};
+
+/// A scored, sorted set capped at a fixed number of items that keeps only the highest
+/// scores, like a top-N leaderboard. Once the set is full, an item is only admitted if its
+/// score is strictly higher than the lowest score held, in which case the earliest added item
+/// with the lowest score is evicted to make room.
+///
+/// Unlike `EvictingScoredSet`, which weighs score against staleness, eviction here depends on
+/// score alone, and a full set rejects low-scoring items instead of inserting and evicting them.
+pub struct BoundedScoredSet<T> {
+    set: ScoredSortedSet<T>,
+    max_len: usize,
+}
+
+/// The outcome of `BoundedScoredSet::add`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Insertion<T> {
+    /// The set had room, and the item was added.
+    Inserted,
+    /// The set was full and the score was not higher than the lowest score held, so the item
+    /// was not added. The rejected item is handed back.
+    Rejected(T),
+    /// The set was full, so the item was added after evicting the returned score and item.
+    InsertedEvicting(i32, T),
+}
+
+impl<T> BoundedScoredSet<T> {
+    /// Creates a new, empty `BoundedScoredSet` holding at most `max_len` items.
+    pub fn new(max_len: usize) -> Self {
+        BoundedScoredSet {
+            set: ScoredSortedSet::new(),
+            max_len,
+        }
+    }
+
+    /// Returns the maximum number of items the set holds.
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// Adds an item with a given score, evicting the earliest added item with the lowest score
+    /// if the set is full and the new score is higher. The capacity check, eviction, and
+    /// insertion happen under a single write lock.
+    pub fn add(&self, score: i32, item: T) -> Insertion<T> {
+        let mut inner = lock::write(&self.set.inner);
+        if self.set.len() < self.max_len {
+            self.set.push_locked(&mut inner, score, item);
+            return Insertion::Inserted;
+        }
+        let lowest = match inner.first_key_value() {
+            Some((&lowest, _)) if lowest < score => lowest,
+            _ => return Insertion::Rejected(item), // Also covers a capacity of zero
+        };
+        let evicted = self.set.take_locked(&mut inner, lowest, 0);
+        self.set.push_locked(&mut inner, score, item);
+        Insertion::InsertedEvicting(lowest, evicted)
+    }
+
+    /// Removes a specified item from the set for a given score.
+    /// Returns `true` if the item was removed.
+    pub fn remove(&self, score: i32, item: &T) -> bool
+    where
+        T: PartialEq + Clone,
+    {
+        self.set.remove(score, item)
+    }
+
+    /// Retrieves a clone of the items associated with a given score.
+    /// Returns `None` if the score does not exist in the set.
+    pub fn get(&self, score: i32) -> Option<Vec<T>>
+    where
+        T: Clone,
+    {
+        self.set.get(score)
+    }
+
+    /// Returns the `n` highest scores and their items, in descending order of score.
+    pub fn highest_scores(&self, n: usize) -> Vec<(i32, Vec<T>)>
+    where
+        T: Clone,
+    {
+        self.set.highest_scores(n)
+    }
+
+    /// Returns the lowest score held, which a new item must beat once the set is full.
+    /// Returns `None` if the set is empty.
+    pub fn lowest_score(&self) -> Option<i32> {
+        lock::read(&self.set.inner).keys().next().copied()
+    }
+
+    /// Returns the total number of items in the set.
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    /// Returns `true` if the set holds no items.
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BoundedScoredSet, Insertion};
+
+    #[test]
+    fn full_set_rejects_lower_scores() {
+        let set = BoundedScoredSet::new(2);
+        assert_eq!(set.add(10, "Alice"), Insertion::Inserted);
+        assert_eq!(set.add(20, "Bob"), Insertion::Inserted);
+
+        assert_eq!(set.add(5, "Charlie"), Insertion::Rejected("Charlie"));
+        assert_eq!(
+            set.add(10, "Dave"),
+            Insertion::Rejected("Dave"),
+            "Tying the lowest score is not enough"
+        );
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.get(10).unwrap(), vec!["Alice"]);
+    }
+
+    #[test]
+    fn full_set_evicts_lowest_for_higher_scores() {
+        let set = BoundedScoredSet::new(3);
+        set.add(10, "Alice");
+        set.add(10, "Bob");
+        set.add(30, "Charlie");
+
+        assert_eq!(
+            set.add(20, "Dave"),
+            Insertion::InsertedEvicting(10, "Alice"),
+            "The earliest added item at the lowest score goes first"
+        );
+        assert_eq!(set.add(40, "Eve"), Insertion::InsertedEvicting(10, "Bob"));
+        assert_eq!(set.lowest_score(), Some(20));
+        assert_eq!(
+            set.highest_scores(3),
+            vec![(40, vec!["Eve"]), (30, vec!["Charlie"]), (20, vec!["Dave"])]
+        );
+        assert_eq!(set.len(), set.max_len());
+    }
+
+    #[test]
+    fn zero_capacity_rejects_everything() {
+        let set = BoundedScoredSet::new(0);
+        assert_eq!(set.add(i32::MAX, "Alice"), Insertion::Rejected("Alice"));
+        assert!(set.is_empty());
+    }
+}
@@ -0,0 +1,75 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A source of the current time, used by `ExpiringScoredSet` to decide which items have
+/// expired.
+pub trait Clock {
+    /// Returns the current instant. Successive calls must never go backwards.
+    fn now(&self) -> Instant;
+}
+
+/// A `Clock` reading the system's monotonic clock through `Instant::now`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A `Clock` that stands still until it is advanced by hand, for deterministic tests of
+/// expiration without sleeping. Clones share the same time, so a test can keep one clone and
+/// hand another to the set under test.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl MockClock {
+    /// Creates a new `MockClock` stopped at the current instant.
+    pub fn new() -> Self {
+        MockClock {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Moves the clock, and every clone of it, forward by `by`.
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Clock, MockClock};
+
+    #[test]
+    fn mock_clock_moves_only_when_advanced() {
+        let clock = MockClock::new();
+        let shared = clock.clone();
+        let start = clock.now();
+        assert_eq!(clock.now(), start);
+
+        shared.advance(Duration::from_secs(5));
+        assert_eq!(
+            clock.now(),
+            start + Duration::from_secs(5),
+            "Clones share the time"
+        );
+    }
+}
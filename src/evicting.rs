@@ -0,0 +1,237 @@
+use std::collections::BTreeMap;
+
+use crate::lock::{self, RwLock};
+
+/// A thread-safe, scored, and sorted set of items with a fixed capacity.
+/// When an insertion pushes the set over capacity, the item with the lowest retention value
+/// is evicted, where an item's retention value is
+///
+/// ```text
+/// retention = score - recency_weight * staleness
+/// ```
+///
+/// `staleness` is measured on a logical clock that advances by one on every `add` and
+/// `increment_score`: it is the number of ticks since the item was last added or incremented.
+/// With a positive `recency_weight`, items that have not been touched for a while become
+/// cheaper to evict even if their score is high; a weight of zero evicts purely by score.
+/// Staleness is subtracted rather than added, so that a positive weight evicts stale items
+/// with high scores: adding it would protect the stalest items instead. A negative weight
+/// gives that opposite behavior, keeping long-untouched items longer than fresh ones.
+/// Ties in retention value are broken by evicting the item with the lower score, then the
+/// one inserted into its score first.
+pub struct EvictingScoredSet<T> {
+    inner: RwLock<State<T>>,
+    max_items: usize,
+    recency_weight: i64,
+}
+
+struct State<T> {
+    map: BTreeMap<i32, Vec<Entry<T>>>,
+    clock: u64, // Logical clock, advanced by every touch
+    len: usize,
+}
+
+struct Entry<T> {
+    item: T,
+    touched: u64, // Clock value when the item was last added or incremented
+}
+
+impl<T> EvictingScoredSet<T> {
+    /// Creates a new, empty `EvictingScoredSet` holding at most `max_items` items, weighing
+    /// staleness against score by `recency_weight`.
+    pub fn new(max_items: usize, recency_weight: i64) -> Self {
+        EvictingScoredSet {
+            inner: RwLock::new(State {
+                map: BTreeMap::new(),
+                clock: 0,
+                len: 0,
+            }),
+            max_items,
+            recency_weight,
+        }
+    }
+
+    /// Adds an item with a given score to the set, marking it as freshly touched.
+    /// Returns the items evicted to stay within capacity, which may include the new item
+    /// itself if it has the lowest retention value.
+    pub fn add(&self, score: i32, item: T) -> Vec<(i32, T)> {
+        let mut state = lock::write(&self.inner);
+        state.clock += 1;
+        let touched = state.clock;
+        state
+            .map
+            .entry(score)
+            .or_default()
+            .push(Entry { item, touched });
+        state.len += 1;
+
+        let mut evicted = Vec::new();
+        while state.len > self.max_items {
+            match self.evict(&mut state) {
+                Some(entry) => evicted.push(entry),
+                None => break,
+            }
+        }
+        evicted
+    }
+
+    /// Adds `delta` to the score of a specified item, marking it as freshly touched.
+    /// Returns the new score, or `None` if the item is not in the set or the new score
+    /// would overflow, in which case no change is made.
+    pub fn increment_score(&self, item: &T, delta: i32) -> Option<i32>
+    where
+        T: PartialEq,
+    {
+        let mut state = lock::write(&self.inner);
+        let (score, pos) = state.map.iter().find_map(|(&score, entries)| {
+            entries
+                .iter()
+                .position(|entry| &entry.item == item)
+                .map(|pos| (score, pos))
+        })?;
+        let new_score = score.checked_add(delta)?;
+
+        let entries = state.map.get_mut(&score).unwrap();
+        let mut entry = entries.remove(pos);
+        if entries.is_empty() {
+            state.map.remove(&score);
+        }
+        state.clock += 1;
+        entry.touched = state.clock;
+        state.map.entry(new_score).or_default().push(entry);
+        Some(new_score)
+    }
+
+    /// Retrieves a clone of the items associated with a given score.
+    /// Returns `None` if the score does not exist in the set.
+    pub fn get(&self, score: i32) -> Option<Vec<T>>
+    where
+        T: Clone,
+    {
+        let state = lock::read(&self.inner);
+        state
+            .map
+            .get(&score)
+            .map(|entries| entries.iter().map(|entry| entry.item.clone()).collect())
+    }
+
+    /// Returns the total number of items in the set.
+    pub fn len(&self) -> usize {
+        lock::read(&self.inner).len
+    }
+
+    /// Returns `true` if the set holds no items.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the current value of the logical clock.
+    pub fn clock(&self) -> u64 {
+        lock::read(&self.inner).clock
+    }
+
+    /// Removes and returns the item with the lowest retention value.
+    fn evict(&self, state: &mut State<T>) -> Option<(i32, T)> {
+        let clock = state.clock;
+        let mut victim: Option<(i64, i32, usize)> = None;
+        for (&score, entries) in &state.map {
+            for (pos, entry) in entries.iter().enumerate() {
+                let staleness = (clock - entry.touched) as i64;
+                let retention =
+                    (score as i64).saturating_sub(self.recency_weight.saturating_mul(staleness));
+                // Strictly lower only, so ties keep the lowest score and earliest position
+                if victim.is_none_or(|(lowest, _, _)| retention < lowest) {
+                    victim = Some((retention, score, pos));
+                }
+            }
+        }
+
+        let (_, score, pos) = victim?;
+        let entries = state.map.get_mut(&score).unwrap();
+        let entry = entries.remove(pos);
+        if entries.is_empty() {
+            state.map.remove(&score);
+        }
+        state.len -= 1;
+        Some((score, entry.item))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EvictingScoredSet;
+
+    #[test]
+    fn evicts_lowest_score_without_recency_weight() {
+        let set = EvictingScoredSet::new(2, 0);
+        assert!(set.add(10, "Alice").is_empty());
+        assert!(set.add(30, "Bob").is_empty());
+
+        let evicted = set.add(20, "Charlie");
+        assert_eq!(
+            evicted,
+            vec![(10, "Alice")],
+            "Lowest score should be evicted"
+        );
+        assert_eq!(set.len(), 2);
+        assert!(set.get(10).is_none());
+    }
+
+    #[test]
+    fn new_item_can_evict_itself() {
+        let set = EvictingScoredSet::new(1, 0);
+        set.add(50, "Alice");
+
+        let evicted = set.add(10, "Bob");
+        assert_eq!(
+            evicted,
+            vec![(10, "Bob")],
+            "A low newcomer should be rejected"
+        );
+        assert_eq!(set.get(50).unwrap(), vec!["Alice"]);
+    }
+
+    #[test]
+    fn stale_high_scorer_is_evicted() {
+        // Clock ticks: Alice at 1, Bob at 2, Charlie at 3, Dave at 4
+        let set = EvictingScoredSet::new(3, 10);
+        set.add(100, "Alice");
+        set.add(80, "Bob");
+        set.add(85, "Charlie");
+
+        // At clock 4: Alice = 100 - 10 * 3 = 70, Bob = 80 - 20 = 60, Charlie = 85 - 10 = 75,
+        // Dave = 90 - 0 = 90, so Bob goes.
+        assert_eq!(set.add(90, "Dave"), vec![(80, "Bob")]);
+
+        // Touching Alice refreshes her, so at clock 6 Charlie is the stalest:
+        // Alice = 101 - 10 = 91, Charlie = 85 - 30 = 55, Dave = 90 - 20 = 70, Eve = 60.
+        assert_eq!(set.increment_score(&"Alice", 1), Some(101));
+        assert_eq!(set.clock(), 5);
+        assert_eq!(set.add(60, "Eve"), vec![(85, "Charlie")]);
+    }
+
+    #[test]
+    fn negative_weight_keeps_stale_items() {
+        let set = EvictingScoredSet::new(2, -10);
+        set.add(50, "Alice");
+        set.add(60, "Bob");
+
+        // At clock 3: Alice = 50 + 10 * 2 = 70, Bob = 60 + 10 = 70, Charlie = 65 + 0 = 65
+        assert_eq!(set.add(65, "Charlie"), vec![(65, "Charlie")]);
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn increment_score_missing_item() {
+        let set = EvictingScoredSet::new(2, 1);
+        set.add(10, "Alice");
+
+        assert_eq!(set.increment_score(&"Bob", 5), None);
+        assert_eq!(
+            set.increment_score(&"Alice", i32::MAX),
+            None,
+            "Overflow is rejected"
+        );
+        assert_eq!(set.get(10).unwrap(), vec!["Alice"]);
+    }
+}
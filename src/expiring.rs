@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::clock::{Clock, SystemClock};
+use crate::lock::{self, RwLock};
+
+/// A thread-safe, scored, and sorted set whose items can expire, such as entries on a live
+/// leaderboard. Items added with `add_with_ttl` expire once their time to live has passed;
+/// items added with `add` never expire.
+///
+/// Expired items are skipped by reads, but they are only removed, and their memory freed, by
+/// `purge_expired`, so `len` counts expired items that have not been purged yet.
+///
+/// The current time is read from the clock `C`, which is the system clock unless another is
+/// given to `with_clock`, such as a `MockClock` in tests.
+pub struct ExpiringScoredSet<T, C = SystemClock> {
+    inner: RwLock<BTreeMap<i32, Vec<Entry<T>>>>,
+    clock: C,
+}
+
+struct Entry<T> {
+    item: T,
+    expires: Option<Instant>, // When the item expires, if ever
+}
+
+impl<T> Entry<T> {
+    /// Returns `true` if the item has expired as of `now`.
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+}
+
+impl<T> ExpiringScoredSet<T> {
+    /// Creates a new, empty `ExpiringScoredSet` using the system clock.
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock)
+    }
+}
+
+impl<T> Default for ExpiringScoredSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, C: Clock> ExpiringScoredSet<T, C> {
+    /// Creates a new, empty `ExpiringScoredSet` that reads the current time from `clock`.
+    pub fn with_clock(clock: C) -> Self {
+        ExpiringScoredSet {
+            inner: RwLock::new(BTreeMap::new()),
+            clock,
+        }
+    }
+
+    /// Adds an item with a given score that never expires.
+    pub fn add(&self, score: i32, item: T) {
+        self.push(score, item, None);
+    }
+
+    /// Adds an item with a given score that expires once `ttl` has passed.
+    /// A `ttl` too large to represent never expires.
+    pub fn add_with_ttl(&self, score: i32, item: T, ttl: Duration) {
+        self.push(score, item, self.clock.now().checked_add(ttl));
+    }
+
+    /// Appends an entry to the items for `score`.
+    fn push(&self, score: i32, item: T, expires: Option<Instant>) {
+        let mut inner = lock::write(&self.inner);
+        inner
+            .entry(score)
+            .or_default()
+            .push(Entry { item, expires });
+    }
+
+    /// Removes every expired item and returns how many were removed.
+    pub fn purge_expired(&self) -> usize {
+        let now = self.clock.now();
+        let mut inner = lock::write(&self.inner);
+        let mut purged = 0;
+        inner.retain(|_, entries| {
+            let before = entries.len();
+            entries.retain(|entry| !entry.is_expired(now));
+            purged += before - entries.len();
+            !entries.is_empty()
+        });
+        purged
+    }
+
+    /// Removes every item equal to `item` from a given score, expired or not.
+    /// Returns `true` if anything was removed.
+    pub fn remove(&self, score: i32, item: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        let mut inner = lock::write(&self.inner);
+        let Some(entries) = inner.get_mut(&score) else {
+            return false;
+        };
+        let before = entries.len();
+        entries.retain(|entry| entry.item != *item);
+        let removed = entries.len() < before;
+        if entries.is_empty() {
+            inner.remove(&score);
+        }
+        removed
+    }
+
+    /// Retrieves a clone of the unexpired items associated with a given score, in insertion
+    /// order. Returns `None` if the score holds no unexpired items.
+    pub fn get(&self, score: i32) -> Option<Vec<T>>
+    where
+        T: Clone,
+    {
+        let now = self.clock.now();
+        let inner = lock::read(&self.inner);
+        let items = live_items(inner.get(&score)?, now);
+        (!items.is_empty()).then_some(items)
+    }
+
+    /// Retrieves the highest score with unexpired items, and those items.
+    /// Returns `None` if the set holds no unexpired items.
+    pub fn highest_score(&self) -> Option<(i32, Vec<T>)>
+    where
+        T: Clone,
+    {
+        let now = self.clock.now();
+        let inner = lock::read(&self.inner);
+        inner.iter().rev().find_map(|(&score, entries)| {
+            let items = live_items(entries, now);
+            (!items.is_empty()).then_some((score, items))
+        })
+    }
+
+    /// Returns the total number of items in the set, including expired items that have not
+    /// been purged yet.
+    pub fn len(&self) -> usize {
+        lock::read(&self.inner).values().map(Vec::len).sum()
+    }
+
+    /// Returns `true` if the set holds no items, expired or not.
+    pub fn is_empty(&self) -> bool {
+        lock::read(&self.inner).is_empty()
+    }
+}
+
+/// Returns clones of the items in `entries` that have not expired as of `now`.
+fn live_items<T: Clone>(entries: &[Entry<T>], now: Instant) -> Vec<T> {
+    entries
+        .iter()
+        .filter(|entry| !entry.is_expired(now))
+        .map(|entry| entry.item.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::ExpiringScoredSet;
+    use crate::MockClock;
+
+    #[test]
+    fn purge_removes_only_expired_items() {
+        let set = ExpiringScoredSet::new();
+        set.add_with_ttl(10, "Alice", Duration::from_millis(20));
+        set.add_with_ttl(10, "Bob", Duration::from_secs(60));
+        set.add_with_ttl(20, "Charlie", Duration::from_millis(20));
+        set.add(30, "Dave");
+
+        assert_eq!(set.purge_expired(), 0, "Nothing has expired yet");
+        thread::sleep(Duration::from_millis(50));
+
+        assert_eq!(set.get(20), None, "Reads skip expired items");
+        assert_eq!(set.len(), 4, "Expired items stay until purged");
+        assert_eq!(set.purge_expired(), 2);
+        assert_eq!(set.get(10).unwrap(), vec!["Bob"]);
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.purge_expired(), 0);
+    }
+
+    #[test]
+    fn highest_score_skips_expired_items() {
+        let set = ExpiringScoredSet::new();
+        set.add(10, "Alice");
+        set.add_with_ttl(20, "Bob", Duration::ZERO);
+
+        assert_eq!(set.highest_score(), Some((10, vec!["Alice"])));
+        assert!(set.remove(20, &"Bob"), "Expired items can still be removed");
+        assert!(!set.remove(20, &"Bob"));
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn mock_clock_expires_items_exactly_at_their_ttl() {
+        let clock = MockClock::new();
+        let set = ExpiringScoredSet::with_clock(clock.clone());
+        set.add_with_ttl(10, "Alice", Duration::from_secs(10));
+        set.add_with_ttl(10, "Bob", Duration::from_secs(30));
+        set.add_with_ttl(20, "Charlie", Duration::from_secs(20));
+        set.add(30, "Dave");
+
+        clock.advance(Duration::from_secs(9));
+        assert_eq!(set.purge_expired(), 0);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(
+            set.purge_expired(),
+            1,
+            "Alice expires at exactly 10 seconds"
+        );
+        assert_eq!(set.get(10).unwrap(), vec!["Bob"]);
+
+        clock.advance(Duration::from_secs(15));
+        assert_eq!(set.get(20), None);
+        assert_eq!(set.highest_score(), Some((30, vec!["Dave"])));
+        assert_eq!(set.purge_expired(), 1);
+
+        clock.advance(Duration::from_secs(3600));
+        assert_eq!(set.purge_expired(), 1, "Only Bob is left to expire");
+        assert_eq!(set.highest_score(), Some((30, vec!["Dave"])));
+        assert_eq!(set.len(), 1);
+    }
+}
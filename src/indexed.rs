@@ -0,0 +1,241 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+use crate::lock::{self, RwLock};
+
+/// A thread-safe, scored, and sorted set that also keeps a `HashMap` from each item to its
+/// score, so looking up, removing, or rescoring an item by value runs in O(1) instead of
+/// scanning every score.
+///
+/// Because the index maps each item to a single score, an item can be stored at most once:
+/// `add` rejects items already in the set, and `update_score` moves them instead. Items are
+/// cloned once on insertion to key the index.
+pub struct IndexedScoredSet<T> {
+    inner: RwLock<State<T>>,
+}
+
+struct State<T> {
+    map: BTreeMap<i32, Vec<T>>,
+    index: HashMap<T, i32>, // Score of every item in `map`
+}
+
+impl<T: Eq + Hash + Clone> IndexedScoredSet<T> {
+    /// Creates a new, empty `IndexedScoredSet`.
+    pub fn new() -> Self {
+        IndexedScoredSet {
+            inner: RwLock::new(State {
+                map: BTreeMap::new(),
+                index: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Adds an item with a given score.
+    /// Returns `false` without changing anything if the item is already in the set.
+    pub fn add(&self, score: i32, item: T) -> bool {
+        let mut state = lock::write(&self.inner);
+        if state.index.contains_key(&item) {
+            return false;
+        }
+        state.index.insert(item.clone(), score);
+        state.map.entry(score).or_default().push(item);
+        true
+    }
+
+    /// Removes a specified item and returns the score it was stored under, or `None` if the
+    /// item is not in the set.
+    pub fn remove(&self, item: &T) -> Option<i32> {
+        let mut state = lock::write(&self.inner);
+        let score = state.index.remove(item)?;
+        state.take(score, item);
+        Some(score)
+    }
+
+    /// Moves a specified item to `new_score`, appending it to the items already there, and
+    /// returns its previous score, or `None` if the item is not in the set.
+    pub fn update_score(&self, item: &T, new_score: i32) -> Option<i32> {
+        let mut state = lock::write(&self.inner);
+        let old_score = *state.index.get(item)?;
+        state.index.insert(item.clone(), new_score);
+        let item = state.take(old_score, item);
+        state.map.entry(new_score).or_default().push(item);
+        Some(old_score)
+    }
+
+    /// Adds `delta` to the score of a specified item and returns the new score.
+    /// Returns `None` without changing anything if the item is not in the set or the new score
+    /// would overflow an `i32`.
+    pub fn increment_score(&self, item: &T, delta: i32) -> Option<i32> {
+        let mut state = lock::write(&self.inner);
+        let old_score = *state.index.get(item)?;
+        let new_score = old_score.checked_add(delta)?;
+        state.index.insert(item.clone(), new_score);
+        let item = state.take(old_score, item);
+        state.map.entry(new_score).or_default().push(item);
+        Some(new_score)
+    }
+
+    /// Returns the score of a specified item, or `None` if the item is not in the set.
+    pub fn score_of(&self, item: &T) -> Option<i32> {
+        lock::read(&self.inner).index.get(item).copied()
+    }
+
+    /// Returns `true` if a specified item is in the set.
+    pub fn contains(&self, item: &T) -> bool {
+        lock::read(&self.inner).index.contains_key(item)
+    }
+
+    /// Retrieves a clone of the items associated with a given score, in insertion order.
+    /// Returns `None` if the score does not exist in the set.
+    pub fn get(&self, score: i32) -> Option<Vec<T>> {
+        lock::read(&self.inner).map.get(&score).cloned()
+    }
+
+    /// Removes and returns the most recently added item with the highest score.
+    /// Returns `None` if the set is empty.
+    pub fn pop_highest(&self) -> Option<(i32, T)> {
+        let mut state = lock::write(&self.inner);
+        let mut entry = state.map.last_entry()?;
+        let score = *entry.key();
+        let item = entry.get_mut().pop().unwrap();
+        if entry.get().is_empty() {
+            entry.remove();
+        }
+        state.index.remove(&item);
+        Some((score, item))
+    }
+
+    /// Removes and returns the earliest added item with the lowest score.
+    /// Returns `None` if the set is empty.
+    pub fn pop_lowest(&self) -> Option<(i32, T)> {
+        let mut state = lock::write(&self.inner);
+        let mut entry = state.map.first_entry()?;
+        let score = *entry.key();
+        let item = entry.get_mut().remove(0);
+        if entry.get().is_empty() {
+            entry.remove();
+        }
+        state.index.remove(&item);
+        Some((score, item))
+    }
+
+    /// Returns the total number of items in the set.
+    pub fn len(&self) -> usize {
+        lock::read(&self.inner).index.len()
+    }
+
+    /// Returns `true` if the set holds no items.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: Eq + Hash + Clone> Default for IndexedScoredSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: PartialEq> State<T> {
+    /// Removes and returns `item` from the items for `score`, removing the score if it has no
+    /// items left. The index must already say the item is at `score`.
+    fn take(&mut self, score: i32, item: &T) -> T {
+        let items = self.map.get_mut(&score).unwrap();
+        let pos = items.iter().position(|current| current == item).unwrap();
+        let item = items.remove(pos);
+        if items.is_empty() {
+            self.map.remove(&score);
+        }
+        item
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::IndexedScoredSet;
+    use crate::lock;
+
+    /// Asserts that the index matches a full scan of the score map.
+    fn assert_index_consistent(set: &IndexedScoredSet<u32>) {
+        let state = lock::read(&set.inner);
+        let mut scanned = HashMap::new();
+        for (&score, items) in &state.map {
+            assert!(!items.is_empty(), "No score should be left empty");
+            for &item in items {
+                assert!(scanned.insert(item, score).is_none(), "Item stored twice");
+            }
+        }
+        assert_eq!(state.index, scanned);
+    }
+
+    #[test]
+    fn lookups_use_the_index() {
+        let set = IndexedScoredSet::new();
+        assert!(set.add(10, "Alice"));
+        assert!(set.add(10, "Bob"));
+        assert!(!set.add(20, "Alice"), "Items are stored at most once");
+
+        assert_eq!(set.score_of(&"Alice"), Some(10));
+        assert_eq!(set.update_score(&"Alice", 30), Some(10));
+        assert_eq!(set.increment_score(&"Alice", 5), Some(35));
+        assert_eq!(set.increment_score(&"Alice", i32::MAX), None);
+        assert_eq!(set.remove(&"Bob"), Some(10));
+        assert!(set.get(10).is_none());
+        assert_eq!(set.remove(&"Bob"), None);
+        assert!(set.contains(&"Alice"));
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn pops_keep_the_index_in_sync() {
+        let set = IndexedScoredSet::new();
+        set.add(10, "Alice");
+        set.add(20, "Bob");
+        set.add(20, "Charlie");
+
+        assert_eq!(set.pop_highest(), Some((20, "Charlie")));
+        assert_eq!(set.pop_lowest(), Some((10, "Alice")));
+        assert!(!set.contains(&"Alice"));
+        assert!(set.add(5, "Alice"), "Popped items can be added again");
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn index_matches_scan_after_random_operations() {
+        let set = IndexedScoredSet::new();
+        let mut seed: u32 = 54321;
+        let mut next = move |bound: u32| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345); // Simple LCG
+            (seed >> 16) % bound
+        };
+
+        for _ in 0..2000 {
+            let item = next(50);
+            let score = next(20) as i32 - 10;
+            match next(6) {
+                0 | 1 => {
+                    set.add(score, item);
+                }
+                2 => {
+                    set.remove(&item);
+                }
+                3 => {
+                    set.update_score(&item, score);
+                }
+                4 => {
+                    set.increment_score(&item, score);
+                }
+                _ => {
+                    if next(2) == 0 {
+                        set.pop_highest();
+                    } else {
+                        set.pop_lowest();
+                    }
+                }
+            }
+            assert_index_consistent(&set);
+        }
+    }
+}
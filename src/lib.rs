@@ -1,96 +1,489 @@
-use std::collections::BTreeMap;
-use std::sync::RwLock;
+mod bounded;
+mod clock;
+mod evicting;
+mod expiring;
+mod indexed;
+mod lock;
+#[cfg(feature = "rand")]
+mod random;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod sharded;
+mod shared;
+
+pub use bounded::{BoundedScoredSet, Insertion};
+pub use clock::{Clock, MockClock, SystemClock};
+pub use evicting::EvictingScoredSet;
+pub use expiring::ExpiringScoredSet;
+pub use indexed::IndexedScoredSet;
+pub use sharded::ShardedScoredSet;
+pub use shared::SharedScoredSet;
+
+use lock::{Mutex, MutexGuard, RwLock, RwLockReadGuard};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{btree_map, BTreeMap, BTreeSet, BinaryHeap, HashMap};
+use std::fmt;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::Bound::{self, Excluded, Included, Unbounded};
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicI64, AtomicU64, AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::OnceLock;
+
+/// Number of integer score units per whole fractional score in `add_f64` and `score_of_f64`.
+const FIXED_POINT_SCALE: f64 = 1000.0;
+
+/// A running sum of all scores, and the function converting each score into its summand.
+type SumCache<S> = (AtomicI64, fn(S) -> i64);
 
 /// A thread-safe, scored, and sorted set of items.
 /// The set uses a BTreeMap to store items with their associated scores.
 /// Items with the same score are stored in a vector.
-pub struct ScoredSortedSet<T> {
-    inner: RwLock<BTreeMap<i32, Vec<T>>>, // Wrap BTreeMap in an RwLock
+///
+/// Scores are `i32` by default, but any ordered `Copy` type can be used as the score type `S`,
+/// such as `i64` timestamps, `Reverse<i32>`, or tuples. Methods that do arithmetic on scores,
+/// such as `sum_of_scores` or `increment_score`, are only available for `i32` scores.
+pub struct ScoredSortedSet<T, S = i32> {
+    inner: RwLock<BTreeMap<S, Vec<T>>>, // Wrap BTreeMap in an RwLock
+    bounds: Option<(S, S)>,             // Inclusive range of allowed scores, if restricted
+    len: AtomicUsize,                   // Number of items across all scores
+    sum: Option<SumCache<S>>,           // Cached sum of all scores, if enabled
+    version: AtomicU64,                 // Bumped on every change to the contents
+    stamps: Option<Mutex<BTreeMap<S, Vec<u64>>>>, // Per-item insertion versions, if tracked
+    reserved: OnceLock<Mutex<BTreeMap<S, usize>>>, // Capacity for unpopulated scores, if reserved
+    unique_scores: bool,                // Whether each score may hold only one item
+    bucket_capacity: usize,             // Initial capacity for newly created scores
 }
 
-impl<T> ScoredSortedSet<T> {
-    /// Creates a new, empty `ScoredSortedSet`.
-    pub fn new() -> Self {
+impl<T, S: Ord + Copy> ScoredSortedSet<T, S> {
+    /// Creates a new, empty `ScoredSortedSet` with scores of type `S`, for example
+    /// `ScoredSortedSet::<String, i64>::with_score_type()`. Use `new` for `i32` scores.
+    pub fn with_score_type() -> Self {
         ScoredSortedSet {
             inner: RwLock::new(BTreeMap::new()),
+            bounds: None,
+            len: AtomicUsize::new(0),
+            sum: None,
+            version: AtomicU64::new(0),
+            stamps: None,
+            reserved: OnceLock::new(),
+            unique_scores: false,
+            bucket_capacity: 0,
+        }
+    }
+
+    /// Creates a new, empty `ScoredSortedSet` that only accepts scores within `[min, max]`.
+    /// Any operation that would place an item outside the bounds is a no-op: `add` and
+    /// `update_score` return `false` and leave the set unchanged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min > max`.
+    pub fn with_score_bounds(min: S, max: S) -> Self {
+        assert!(min <= max, "score bounds must satisfy min <= max");
+        ScoredSortedSet {
+            bounds: Some((min, max)),
+            ..Self::with_score_type()
+        }
+    }
+
+    /// Creates a new, empty `ScoredSortedSet` that records the version at which each item was
+    /// inserted, so that `items_since` can report the items added after a given version.
+    /// Every mutation pays a small bookkeeping cost to keep the records current.
+    pub fn with_insertion_tracking() -> Self {
+        ScoredSortedSet {
+            stamps: Some(Mutex::new(BTreeMap::new())),
+            ..Self::with_score_type()
+        }
+    }
+
+    /// Creates a new, empty `ScoredSortedSet` in which no two items share a score, for strict
+    /// priority orders without ties. Any operation that would place an item at a score already
+    /// held by another item is a no-op: `add` and `update_score` return `false` and leave the
+    /// set unchanged. Every score therefore holds exactly one item, so the vectors returned by
+    /// `get` always have length 1.
+    pub fn with_unique_scores() -> Self {
+        ScoredSortedSet {
+            unique_scores: true,
+            ..Self::with_score_type()
+        }
+    }
+
+    /// Creates a new, empty `ScoredSortedSet` that allocates room for `hint` items whenever a
+    /// score gains its first item, avoiding repeated growth when many items share a score.
+    /// Capacity reserved for a score with `prepare_scores` takes precedence over the hint.
+    pub fn with_capacity_per_score(hint: usize) -> Self {
+        ScoredSortedSet {
+            bucket_capacity: hint,
+            ..Self::with_score_type()
+        }
+    }
+
+    /// Acquires a read lock on the underlying score map and returns its guard.
+    ///
+    /// This is a low-level escape hatch for callers that need to hold a guard across several
+    /// of their own reads. Prefer the methods on `ScoredSortedSet` where possible. Calling
+    /// methods that write to the set while holding the guard may deadlock.
+    ///
+    /// # Panics
+    ///
+    /// Without the `parking_lot` feature, panics if the lock is poisoned.
+    pub fn raw_read(&self) -> impl Deref<Target = BTreeMap<S, Vec<T>>> + '_ {
+        lock::read(&self.inner)
+    }
+
+    /// Acquires a write lock on the underlying score map and returns its guard.
+    ///
+    /// This is a low-level escape hatch for callers that need to hold a guard across several
+    /// of their own operations. Prefer the methods on `ScoredSortedSet` where possible. Callers
+    /// that write through this guard must uphold the set's invariants:
+    ///
+    /// - No score maps to an empty vector; remove the score once its last item is gone.
+    /// - Every score lies within the set's score bounds, if any were configured.
+    /// - No score holds more than one item in a set created with `with_unique_scores`.
+    ///
+    /// Changes made through this guard bypass `len`, `version`, the cached sum of a set created
+    /// with `with_cached_aggregates`, and the insertion records of a set created with
+    /// `with_insertion_tracking`, which will then report stale values.
+    ///
+    /// Calling other methods on the set while holding the guard may deadlock.
+    ///
+    /// # Panics
+    ///
+    /// Without the `parking_lot` feature, panics if the lock is poisoned.
+    pub fn raw_write(&self) -> impl DerefMut<Target = BTreeMap<S, Vec<T>>> + '_ {
+        lock::write(&self.inner)
+    }
+
+    /// Returns the set's version, a counter that increases every time its contents change.
+    /// Callers can cache query results alongside the version and skip re-fetching while it
+    /// stays the same. Calls that change nothing, such as removing an absent item, leave the
+    /// version unchanged, but calls that look like no-ops yet still modify the set do bump
+    /// it; for example, `update_score` with equal old and new scores moves the item to the
+    /// end of its score's items.
+    pub fn version(&self) -> u64 {
+        self.version.load(AtomicOrdering::Relaxed)
+    }
+
+    /// Returns `true` if `score` is allowed by the set's score bounds.
+    fn in_bounds(&self, score: S) -> bool {
+        match self.bounds {
+            Some((min, max)) => (min..=max).contains(&score),
+            None => true,
+        }
+    }
+
+    /// Creates a new set holding `map`, with the same configuration as this one.
+    fn new_like(&self, map: BTreeMap<S, Vec<T>>) -> Self {
+        ScoredSortedSet {
+            len: AtomicUsize::new(map.values().map(Vec::len).sum()),
+            sum: (self.sum.as_ref())
+                .map(|&(_, value)| (AtomicI64::new(sum_of(&map, value)), value)),
+            stamps: self.stamps.as_ref().map(|_| Mutex::new(zero_stamps(&map))),
+            inner: RwLock::new(map),
+            bounds: self.bounds,
+            version: AtomicU64::new(0),
+            reserved: OnceLock::new(),
+            unique_scores: self.unique_scores,
+            bucket_capacity: self.bucket_capacity,
+        }
+    }
+
+    /// Returns `true` if `score` is held by an item in a set created with `with_unique_scores`,
+    /// so that no other item may be placed there. Must be called with a lock held.
+    fn is_taken(&self, inner: &BTreeMap<S, Vec<T>>, score: S) -> bool {
+        self.unique_scores && inner.contains_key(&score)
+    }
+
+    /// Returns the items for `score`, creating the score with any capacity reserved for it by
+    /// `prepare_scores`, or else the set's per-score capacity hint, if needed. Must be called
+    /// with the write lock held, and the caller must add at least one item so that no empty
+    /// score is left behind.
+    fn bucket_locked<'a>(&self, inner: &'a mut BTreeMap<S, Vec<T>>, score: S) -> &'a mut Vec<T> {
+        match inner.entry(score) {
+            btree_map::Entry::Occupied(entry) => entry.into_mut(),
+            btree_map::Entry::Vacant(entry) => {
+                let reserved = self
+                    .reservations()
+                    .and_then(|mut reserved| reserved.remove(&score));
+                let capacity = reserved.unwrap_or(self.bucket_capacity);
+                entry.insert(Vec::with_capacity(capacity))
+            }
+        }
+    }
+
+    /// Locks the capacity reservations made by `prepare_scores`, or returns `None` without
+    /// locking anything if none were ever made, so sets that never reserve pay nothing.
+    /// Must be called with the write lock held, so the mutex is never contended.
+    fn reservations(&self) -> Option<MutexGuard<'_, BTreeMap<S, usize>>> {
+        let reserved = self.reserved.get()?;
+        Some(lock::lock(reserved))
+    }
+
+    /// Appends `item` to the items for `score`, creating the score if needed.
+    /// Must be called with the write lock held.
+    fn push_locked(&self, inner: &mut BTreeMap<S, Vec<T>>, score: S, item: T) {
+        self.bucket_locked(inner, score).push(item);
+        self.record_added(score, 1);
+        if let Some(stamps) = &self.stamps {
+            let version = self.version.load(AtomicOrdering::Relaxed);
+            lock::lock(stamps).entry(score).or_default().push(version);
+        }
+    }
+
+    /// Removes and returns the item at `pos` among the items for `score`, removing the score
+    /// if it has no items left. Must be called with the write lock held.
+    fn take_locked(&self, inner: &mut BTreeMap<S, Vec<T>>, score: S, pos: usize) -> T {
+        let items = inner.get_mut(&score).unwrap();
+        let item = items.remove(pos);
+        if items.is_empty() {
+            inner.remove(&score);
+        }
+        self.record_removed(score, 1);
+        if let Some(stamps) = &self.stamps {
+            let mut stamps = lock::lock(stamps);
+            if let Some(bucket) = stamps.get_mut(&score) {
+                if pos < bucket.len() {
+                    bucket.remove(pos);
+                }
+                if bucket.is_empty() {
+                    stamps.remove(&score);
+                }
+            }
+        }
+        item
+    }
+
+    /// Removes and returns the items for `score` for which `take` returns `true`, in insertion
+    /// order, removing the score if it has no items left. Must be called with the write lock
+    /// held.
+    fn extract_locked<F>(&self, inner: &mut BTreeMap<S, Vec<T>>, score: S, mut take: F) -> Vec<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let Some(items) = inner.get_mut(&score) else {
+            return Vec::new();
+        };
+        let taken: Vec<T> = match &self.stamps {
+            None => items.extract_if(.., |item| take(item)).collect(),
+            Some(stamps) => {
+                // Decide once per item so the insertion records can be pruned to match
+                let flags: Vec<bool> = items.iter().map(&mut take).collect();
+                let mut next = flags.iter();
+                let taken = items.extract_if(.., |_| *next.next().unwrap()).collect();
+                let mut stamps = lock::lock(stamps);
+                if let Some(bucket) = stamps.get_mut(&score) {
+                    let mut next = flags.iter();
+                    bucket.retain(|_| !next.next().copied().unwrap_or(false));
+                    if bucket.is_empty() {
+                        stamps.remove(&score);
+                    }
+                }
+                taken
+            }
+        };
+        if items.is_empty() {
+            inner.remove(&score);
+        }
+        self.record_removed(score, taken.len());
+        taken
+    }
+
+    /// Removes and returns all items for `score`. Must be called with the write lock held.
+    fn take_bucket_locked(&self, inner: &mut BTreeMap<S, Vec<T>>, score: S) -> Option<Vec<T>> {
+        let items = inner.remove(&score)?;
+        self.record_removed(score, items.len());
+        if let Some(stamps) = &self.stamps {
+            lock::lock(stamps).remove(&score);
+        }
+        Some(items)
+    }
+
+    /// Appends `items` to the items for `score`, creating the score if needed.
+    /// Must be called with the write lock held.
+    fn append_bucket_locked(&self, inner: &mut BTreeMap<S, Vec<T>>, score: S, items: Vec<T>) {
+        let n = items.len();
+        if n == 0 {
+            return; // Never create an empty score
+        }
+        self.bucket_locked(inner, score).extend(items);
+        self.record_added(score, n);
+        if let Some(stamps) = &self.stamps {
+            let version = self.version.load(AtomicOrdering::Relaxed);
+            let mut stamps = lock::lock(stamps);
+            stamps
+                .entry(score)
+                .or_default()
+                .extend(std::iter::repeat_n(version, n));
+        }
+    }
+
+    /// Updates the length, version, and cached sum after `n` items were added at `score`.
+    /// Must be called with the write lock held.
+    fn record_added(&self, score: S, n: usize) {
+        if n == 0 {
+            return;
+        }
+        self.len.fetch_add(n, AtomicOrdering::Relaxed);
+        self.version.fetch_add(1, AtomicOrdering::Relaxed);
+        if let Some((sum, value)) = &self.sum {
+            sum.fetch_add(value(score) * n as i64, AtomicOrdering::Relaxed);
+        }
+    }
+
+    /// Updates the length, version, and cached sum after `n` items were removed from `score`.
+    /// Must be called with the write lock held.
+    fn record_removed(&self, score: S, n: usize) {
+        if n == 0 {
+            return;
+        }
+        self.len.fetch_sub(n, AtomicOrdering::Relaxed);
+        self.version.fetch_add(1, AtomicOrdering::Relaxed);
+        if let Some((sum, value)) = &self.sum {
+            sum.fetch_sub(value(score) * n as i64, AtomicOrdering::Relaxed);
+        }
+    }
+
+    /// Updates the length, version, cached sum, and insertion records after the items for
+    /// `score` were edited in place from `before` items to `after`, removing the score if it
+    /// is left empty. Each remaining item keeps the insertion record of an equal item among
+    /// the `original` items, which must be given when insertions are tracked, and any other
+    /// item counts as newly inserted. Must be called with the write lock held.
+    fn resize_locked(
+        &self,
+        inner: &mut BTreeMap<S, Vec<T>>,
+        score: S,
+        before: usize,
+        after: usize,
+        original: Option<&[T]>,
+    ) where
+        T: PartialEq,
+    {
+        if after == 0 {
+            inner.remove(&score);
+        }
+        match after.cmp(&before) {
+            Ordering::Greater => self.record_added(score, after - before),
+            Ordering::Less => self.record_removed(score, before - after),
+            Ordering::Equal => {
+                self.version.fetch_add(1, AtomicOrdering::Relaxed); // The items may be reordered
+            }
+        }
+        if let Some(stamps) = &self.stamps {
+            let version = self.version.load(AtomicOrdering::Relaxed);
+            let mut stamps = lock::lock(stamps);
+            let mut unclaimed: Vec<Option<u64>> = match stamps.remove(&score) {
+                Some(bucket) => bucket.into_iter().map(Some).collect(),
+                None => Vec::new(),
+            };
+            if let Some(items) = inner.get(&score) {
+                let original = original.expect("original items are kept while tracking");
+                let bucket = items
+                    .iter()
+                    .map(|item| {
+                        // Claim the record of the first unclaimed equal original item
+                        (original.iter().zip(&mut unclaimed))
+                            .find_map(|(old, stamp)| if old == item { stamp.take() } else { None })
+                            .unwrap_or(version)
+                    })
+                    .collect();
+                stamps.insert(score, bucket);
+            }
         }
     }
 
     /// Adds an item with a given score to the set.
     /// If the score already exists, the item is appended to the vector of items for that score.
-    pub fn add(&self, score: i32, item: T) {
-        let mut inner = self.inner.write().unwrap(); // Lock the RwLock for writing
-        inner.entry(score).or_insert_with(Vec::new).push(item);
+    /// Returns `false` without adding the item if the score is outside the set's score bounds,
+    /// or if the set was created with `with_unique_scores` and the score is already taken.
+    pub fn add(&self, score: S, item: T) -> bool {
+        if !self.in_bounds(score) {
+            return false;
+        }
+        let mut inner = lock::write(&self.inner); // Lock the RwLock for writing
+        if self.is_taken(&inner, score) {
+            return false;
+        }
+        self.push_locked(&mut inner, score, item);
+        true
     }
 
     /// Removes a specified item from the set for a given score.
     /// Returns `true` if the item was successfully removed, `false` otherwise.
     /// If the vector of items for that score becomes empty, the score is removed from the set.
-    pub fn remove(&self, score: i32, item: &T) -> bool
+    pub fn remove(&self, score: S, item: &T) -> bool
     where
         T: PartialEq + Clone, // Clone trait bound added for item removal
     {
-        let mut item_removed = false;
-        let mut inner = self.inner.write().unwrap(); // Acquiring a write lock
-
-        if let Some(items) = inner.get_mut(&score) {
-            let initial_len = items.len();
-            items.retain(|current_item| {
-                if current_item == item {
-                    item_removed = true; // Item found and will be removed
-                    false // Do not retain this item
-                } else {
-                    true // Retain other items
-                }
-            });
-            if items.is_empty() {
-                inner.remove(&score);
-            } else if initial_len == items.len() {
-                // If the lengths are equal, no item was removed
-                item_removed = false;
-            }
-        }
-
-        item_removed
+        let mut inner = lock::write(&self.inner); // Acquiring a write lock
+        let removed = self.extract_locked(&mut inner, score, |current_item| current_item == item);
+        !removed.is_empty() // If nothing was removed, the item was not present
     }
 
     /// Updates the score of a specified item.
     /// The item is first removed from the old score and then added to the new score.
-    /// If the item does not exist at the old score, no change is made.
-    pub fn update_score(&self, old_score: i32, new_score: i32, item: &T)
+    /// If the item does not exist at the old score, the new score is outside the set's score
+    /// bounds, or the set was created with `with_unique_scores` and another item holds the new
+    /// score, no change is made.
+    /// Returns `true` if the item was moved.
+    pub fn update_score(&self, old_score: S, new_score: S, item: &T) -> bool
     where
         T: PartialEq + Clone,
     {
-        let mut inner = self.inner.write().unwrap();
+        if !self.in_bounds(new_score) {
+            return false;
+        }
+        let mut inner = lock::write(&self.inner);
+        if new_score != old_score && self.is_taken(&inner, new_score) {
+            return false;
+        }
 
-        if let Some(items) = inner.get_mut(&old_score) {
+        if let Some(items) = inner.get(&old_score) {
             if let Some(pos) = items.iter().position(|x| x == item) {
-                let item = items.remove(pos);
-                if items.is_empty() {
-                    inner.remove(&old_score);
-                }
-                inner.entry(new_score).or_insert_with(Vec::new).push(item);
+                let item = self.take_locked(&mut inner, old_score, pos);
+                self.push_locked(&mut inner, new_score, item);
+                return true;
             }
         }
+        false
     }
 
     /// Retrieves a clone of the items associated with a given score.
     /// Returns `None` if the score does not exist in the set.
-    pub fn get(&self, score: i32) -> Option<Vec<T>>
+    pub fn get(&self, score: S) -> Option<Vec<T>>
     where
         T: Clone, // Ensure T can be cloned
     {
-        let inner = self.inner.read().unwrap(); // Lock the RwLock for reading
+        let inner = lock::read(&self.inner); // Lock the RwLock for reading
         inner.get(&score).cloned() // Clone the result to avoid borrowing issues
     }
 
+    /// Retrieves a clone of the items associated with a given score, or an empty vector if the
+    /// score does not exist in the set. Use `get` to tell an absent score apart.
+    pub fn get_or_empty(&self, score: S) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.get(score).unwrap_or_default()
+    }
+
+    /// Retrieves the item count and a clone of the items for a given score from one consistent
+    /// read, as `(count, items)`.
+    /// Returns `None` if the score does not exist in the set.
+    pub fn tier(&self, score: S) -> Option<(usize, Vec<T>)>
+    where
+        T: Clone,
+    {
+        let inner = lock::read(&self.inner);
+        inner.get(&score).map(|items| (items.len(), items.clone()))
+    }
+
     /// Returns a vector containing the top `n` highest scores and their associated items.
     /// The vector is sorted in descending order of scores.
-    pub fn highest_scores(&self, n: usize) -> Vec<(i32, Vec<T>)>
+    pub fn highest_scores(&self, n: usize) -> Vec<(S, Vec<T>)>
     where
         T: Clone, // Ensure T can be cloned
     {
-        let inner = self.inner.read().unwrap();
+        let inner = lock::read(&self.inner);
         inner
             .iter()
             .rev() // Reverse iterator to start from the highest score
@@ -99,371 +492,5056 @@ impl<T> ScoredSortedSet<T> {
             .collect()
     }
 
+    /// Returns a vector containing the `n` lowest scores and their associated items.
+    /// The vector is sorted in ascending order of scores.
+    pub fn lowest_scores(&self, n: usize) -> Vec<(S, Vec<T>)>
+    where
+        T: Clone,
+    {
+        let inner = lock::read(&self.inner);
+        inner
+            .iter()
+            .take(n) // Take the n lowest scores
+            .map(|(&score, items)| (score, items.clone()))
+            .collect()
+    }
+
+    /// Returns the top `n` highest scores, each paired with a single representative item (the
+    /// first inserted at that score), sorted in descending order of scores.
+    /// Unlike `highest_scores`, only one item is cloned per score.
+    pub fn tier_previews(&self, n: usize) -> Vec<(S, T)>
+    where
+        T: Clone,
+    {
+        let inner = lock::read(&self.inner);
+        inner
+            .iter()
+            .rev()
+            .take(n)
+            .map(|(&score, items)| (score, items[0].clone()))
+            .collect()
+    }
+
     /// Retrieves the highest score and its associated items.
     /// Returns `None` if the set is empty.
-    pub fn highest_score(&self) -> Option<(i32, Vec<T>)>
+    pub fn highest_score(&self) -> Option<(S, Vec<T>)>
     where
         T: Clone, // Ensure T can be cloned
     {
-        let inner = self.inner.read().unwrap();
+        let inner = lock::read(&self.inner);
         inner
             .iter()
-            .rev()
-            .next()
+            .next_back()
             .map(|(&score, items)| (score, items.clone()))
     }
 
     /// Retrieves the lowest score and its associated items.
     /// Returns `None` if the set is empty.
-    pub fn lowest_score(&self) -> Option<(i32, Vec<T>)>
+    pub fn lowest_score(&self) -> Option<(S, Vec<T>)>
     where
         T: Clone, // Ensure T can be cloned
     {
-        let inner = self.inner.read().unwrap();
+        let inner = lock::read(&self.inner);
         inner
             .iter()
             .next()
             .map(|(&score, items)| (score, items.clone()))
     }
 
+    /// Returns the earliest added item with the lowest score, cloning only that item rather
+    /// than every item tied at the score. Returns `None` if the set is empty.
+    /// This is the item `pop_lowest` would remove.
+    pub fn first_item(&self) -> Option<(S, T)>
+    where
+        T: Clone,
+    {
+        let inner = lock::read(&self.inner);
+        let (&score, items) = inner.first_key_value()?;
+        Some((score, items[0].clone()))
+    }
+
+    /// Returns the most recently added item with the highest score, cloning only that item
+    /// rather than every item tied at the score. Returns `None` if the set is empty.
+    /// This is the item `pop_highest` would remove.
+    pub fn last_item(&self) -> Option<(S, T)>
+    where
+        T: Clone,
+    {
+        let inner = lock::read(&self.inner);
+        let (&score, items) = inner.last_key_value()?;
+        Some((score, items[items.len() - 1].clone()))
+    }
+
     /// Returns a vector containing all the scores in the set in ascending order.
-    pub fn all_scores(&self) -> Vec<i32> {
-        let inner = self.inner.read().unwrap();
+    pub fn all_scores(&self) -> Vec<S> {
+        let inner = lock::read(&self.inner);
         inner.keys().cloned().collect()
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::ScoredSortedSet;
+    /// Returns the total number of items in the set, counting every item under every score.
+    /// The count is maintained by every mutation, so this runs in O(1) without taking the lock.
+    pub fn len(&self) -> usize {
+        self.len.load(AtomicOrdering::Relaxed)
+    }
 
-    #[test]
-    fn test_add_and_get() {
-        let set: ScoredSortedSet<String> = ScoredSortedSet::new();
-        set.add(10, "Alice".to_string());
+    /// Returns `true` if the set holds no items.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 
-        let items = set.get(10).unwrap();
-        assert_eq!(items, vec!["Alice".to_string()]);
+    /// Returns the populated scores within `[min, max]` in ascending order, without their items.
+    /// Returns an empty vector if `min > max`.
+    pub fn scores_in_range(&self, min: S, max: S) -> Vec<S> {
+        if min > max {
+            return Vec::new(); // BTreeMap::range panics on inverted bounds
+        }
+        let inner = lock::read(&self.inner);
+        inner.range(min..=max).map(|(&score, _)| score).collect()
     }
 
-    #[test]
-    fn test_remove() {
-        let set: ScoredSortedSet<String> = ScoredSortedSet::new();
-        set.add(15, "Bob".to_string());
-        set.add(15, "Charlie".to_string());
+    /// Returns `true` if no item has a score within `[min, max]`, stopping at the first
+    /// populated score found. Returns `true` if `min > max`.
+    pub fn range_is_empty(&self, min: S, max: S) -> bool {
+        if min > max {
+            return true; // BTreeMap::range panics on inverted bounds
+        }
+        let inner = lock::read(&self.inner);
+        inner.range(min..=max).next().is_none()
+    }
 
-        set.remove(15, &"Bob".to_string());
+    /// Returns how many items have a score within `[min, max]`, visiting only the matching
+    /// scores. Returns 0 if `min > max`.
+    pub fn count_in_range(&self, min: S, max: S) -> usize {
+        if min > max {
+            return 0; // BTreeMap::range panics on inverted bounds
+        }
+        let inner = lock::read(&self.inner);
+        inner.range(min..=max).map(|(_, items)| items.len()).sum()
+    }
 
-        let items = set.get(15).unwrap();
-        assert_eq!(items, vec!["Charlie".to_string()]);
+    /// Returns how many distinct scores within `[min, max]` hold items, counting scores rather
+    /// than items, unlike `count_in_range`. Returns 0 if `min > max`.
+    pub fn distinct_scores_in_range(&self, min: S, max: S) -> usize {
+        if min > max {
+            return 0; // BTreeMap::range panics on inverted bounds
+        }
+        let inner = lock::read(&self.inner);
+        inner.range(min..=max).count()
     }
 
-    #[test]
-    fn test_remove_nonexistent() {
-        let set: ScoredSortedSet<String> = ScoredSortedSet::new();
-        set.add(20, "Dave".to_string());
+    /// Returns every item with a score within `[min, max]` as `(score, item)` pairs, in
+    /// ascending order, cloning only the matching items. Returns an empty vector if `min > max`.
+    pub fn range_by_score(&self, min: S, max: S) -> Vec<(S, T)>
+    where
+        T: Clone,
+    {
+        self.range_by_score_bounds(Included(min), Included(max))
+    }
 
-        // Attempt to remove an item that doesn't exist
-        set.remove(20, &"Eve".to_string());
+    /// Returns every item with a score between `start` and `end` as `(score, item)` pairs, in
+    /// ascending order, with each bound inclusive, exclusive, or unbounded.
+    /// Returns an empty vector if the bounds describe an empty range, including inverted bounds.
+    pub fn range_by_score_bounds(&self, start: Bound<S>, end: Bound<S>) -> Vec<(S, T)>
+    where
+        T: Clone,
+    {
+        if is_empty_range(start, end) {
+            return Vec::new(); // BTreeMap::range panics on inverted bounds
+        }
+        let inner = lock::read(&self.inner);
+        inner
+            .range((start, end))
+            .flat_map(|(&score, items)| items.iter().map(move |item| (score, item.clone())))
+            .collect()
+    }
 
-        let items = set.get(20).unwrap();
-        assert_eq!(items, vec!["Dave".to_string()]);
+    /// Returns how many items have a score less than or equal to `score`.
+    /// This is the rank a hypothetical item with that score would have, without inserting it.
+    pub fn rank_for_score(&self, score: S) -> usize {
+        let inner = lock::read(&self.inner);
+        inner.range(..=score).map(|(_, items)| items.len()).sum()
+    }
+
+    /// Returns `true` if adding an item at `score` would create a new highest score, i.e. if
+    /// `score` is above the current highest score or the set is empty. The set is not modified.
+    pub fn would_change_leader(&self, score: S) -> bool {
+        let inner = lock::read(&self.inner);
+        inner.keys().next_back().is_none_or(|&max| score > max)
+    }
+
+    /// Splits a copy of the set at `score` into two new, independent sets `(below, at_or_above)`.
+    /// The first holds every item scored below `score`, the second every item scored at or
+    /// above it. The original set is left untouched, and both new sets keep its score bounds.
+    pub fn partition_by_score(&self, score: S) -> (Self, Self)
+    where
+        T: Clone,
+    {
+        let inner = lock::read(&self.inner);
+        let mut below = inner.clone();
+        let at_or_above = below.split_off(&score);
+        drop(inner);
+        (self.new_like(below), self.new_like(at_or_above))
+    }
+
+    /// Returns every item with a unique rank, in ascending order of score.
+    /// Items sharing a score are ordered with `tie_break`, so ranks run from 1 to N with no ties.
+    /// Items that `tie_break` considers equal keep their insertion order.
+    pub fn strict_ranking<F>(&self, tie_break: F) -> Vec<(usize, S, T)>
+    where
+        T: Clone,
+        F: Fn(&T, &T) -> Ordering,
+    {
+        let inner = lock::read(&self.inner);
+        let mut ranking = Vec::new();
+        for (&score, items) in inner.iter() {
+            let mut tied: Vec<&T> = items.iter().collect();
+            tied.sort_by(|a, b| tie_break(a, b)); // Stable sort keeps insertion order for equal items
+            for item in tied {
+                ranking.push((ranking.len() + 1, score, item.clone()));
+            }
+        }
+        ranking
+    }
+
+    /// Returns a map from each item to its zero-based rank in ascending order of score.
+    /// Items sharing a score are ranked in insertion order.
+    /// If the same item value appears more than once, the last write wins, so it maps to the
+    /// highest rank among its occurrences.
+    pub fn rank_map(&self) -> HashMap<T, usize>
+    where
+        T: Hash + Eq + Clone,
+    {
+        let inner = lock::read(&self.inner);
+        inner
+            .values()
+            .flatten()
+            .enumerate()
+            .map(|(rank, item)| (item.clone(), rank))
+            .collect()
+    }
+
+    /// Returns the `k` lowest-ranked individual items as `(score, item)` pairs, in ascending order.
+    /// Items sharing a score keep their insertion order, and a tie group that straddles the
+    /// cutoff contributes only enough items to reach `k`.
+    pub fn bottom_items(&self, k: usize) -> Vec<(S, T)>
+    where
+        T: Clone,
+    {
+        let inner = lock::read(&self.inner);
+        inner
+            .iter()
+            .flat_map(|(&score, items)| items.iter().map(move |item| (score, item)))
+            .take(k) // Stop before cloning anything past the cutoff
+            .map(|(score, item)| (score, item.clone()))
+            .collect()
+    }
+
+    /// Returns the highest-scored item for each distinct key produced by `key_fn`, together
+    /// with its score. When several items with the same key share the best score, the one
+    /// inserted first is kept.
+    pub fn best_per_key<K, F>(&self, key_fn: F) -> HashMap<K, (S, T)>
+    where
+        T: Clone,
+        K: Hash + Eq,
+        F: Fn(&T) -> K,
+    {
+        let inner = lock::read(&self.inner);
+        let mut best = HashMap::new();
+        for (&score, items) in inner.iter().rev() {
+            for item in items {
+                // Scores are visited in descending order, so the first item seen per key is its best
+                best.entry(key_fn(item))
+                    .or_insert_with(|| (score, item.clone()));
+            }
+        }
+        best
+    }
+
+    /// Returns whether two items share the same score: `Some(true)` if both are present at the
+    /// same score, `Some(false)` if both are present at different scores, and `None` if either
+    /// is missing. An item stored under several scores is judged by its lowest score.
+    pub fn are_tied(&self, a: &T, b: &T) -> Option<bool>
+    where
+        T: PartialEq,
+    {
+        let inner = lock::read(&self.inner);
+        let (score_a, _) = locate(&inner, a)?;
+        let (score_b, _) = locate(&inner, b)?;
+        Some(score_a == score_b)
+    }
+
+    /// Returns every item whose score is one of `scores`, as `(score, item)` pairs in ascending
+    /// order of score. Scores absent from the set are skipped, and a score listed more than
+    /// once is only included once.
+    pub fn get_scores(&self, scores: &[S]) -> Vec<(S, T)>
+    where
+        T: Clone,
+    {
+        let mut wanted = scores.to_vec();
+        wanted.sort_unstable();
+        wanted.dedup();
+
+        let inner = lock::read(&self.inner);
+        wanted
+            .into_iter()
+            .filter_map(|score| inner.get(&score).map(|items| (score, items)))
+            .flat_map(|(score, items)| items.iter().map(move |item| (score, item.clone())))
+            .collect()
+    }
+
+    /// Consumes the set and returns each score with its items, in ascending order of score.
+    /// Items are moved out rather than cloned.
+    pub fn into_grouped(self) -> Vec<(S, Vec<T>)> {
+        lock::into_inner(self.inner).into_iter().collect()
+    }
+
+    /// Consumes the set and returns every item as a `(score, item)` pair in descending order
+    /// of score, with items sharing a score in reverse insertion order.
+    /// Items are moved out rather than cloned.
+    pub fn into_sorted_vec_desc(self) -> Vec<(S, T)> {
+        let inner = lock::into_inner(self.inner);
+        let mut sorted = Vec::with_capacity(self.len.into_inner());
+        for (score, items) in inner.into_iter().rev() {
+            sorted.extend(items.into_iter().rev().map(|item| (score, item)));
+        }
+        sorted
+    }
+
+    /// Consumes the set and returns its items in a max-heap of `(score, item)` pairs, so the
+    /// highest score pops first. Items sharing a score pop in descending order of `T`'s own
+    /// ordering, not insertion order. Items are moved out rather than cloned.
+    pub fn into_max_heap(self) -> BinaryHeap<(S, T)>
+    where
+        T: Ord,
+    {
+        self.into_grouped()
+            .into_iter()
+            .flat_map(|(score, items)| items.into_iter().map(move |item| (score, item)))
+            .collect()
+    }
+
+    /// Consumes the set and returns its items in a min-heap of `(score, item)` pairs, so the
+    /// lowest score pops first. Items sharing a score pop in ascending order of `T`'s own
+    /// ordering, not insertion order. Items are moved out rather than cloned.
+    pub fn into_min_heap(self) -> BinaryHeap<Reverse<(S, T)>>
+    where
+        T: Ord,
+    {
+        self.into_grouped()
+            .into_iter()
+            .flat_map(|(score, items)| items.into_iter().map(move |item| Reverse((score, item))))
+            .collect()
+    }
+
+    /// Returns how many scores are shared by at least `k` items.
+    pub fn count_scores_with_at_least(&self, k: usize) -> usize {
+        let inner = lock::read(&self.inner);
+        inner.values().filter(|items| items.len() >= k).count()
+    }
+
+    /// Returns the item ranked immediately above a specified item, with its score.
+    /// Items later in insertion order rank above earlier items with the same score, so the
+    /// next item in the same score is returned before moving on to the next higher score.
+    /// Returns `None` if the item is the highest-ranked item or is not in the set.
+    pub fn item_ahead(&self, item: &T) -> Option<(S, T)>
+    where
+        T: PartialEq + Clone,
+    {
+        let inner = lock::read(&self.inner);
+        let (score, pos) = locate(&inner, item)?;
+        if let Some(next) = inner[&score].get(pos + 1) {
+            return Some((score, next.clone()));
+        }
+        inner
+            .range((Excluded(score), Unbounded))
+            .next()
+            .map(|(&score, items)| (score, items[0].clone()))
+    }
+
+    /// Returns the item ranked immediately below a specified item, with its score.
+    /// This is the mirror of `item_ahead`: the previous item in the same score is returned
+    /// before moving on to the next lower score.
+    /// Returns `None` if the item is the lowest-ranked item or is not in the set.
+    pub fn item_behind(&self, item: &T) -> Option<(S, T)>
+    where
+        T: PartialEq + Clone,
+    {
+        let inner = lock::read(&self.inner);
+        let (score, pos) = locate(&inner, item)?;
+        if pos > 0 {
+            return Some((score, inner[&score][pos - 1].clone()));
+        }
+        inner
+            .range(..score)
+            .next_back()
+            .map(|(&score, items)| (score, items[items.len() - 1].clone()))
+    }
+
+    /// Sets the score of each item in `updates` under a single write lock.
+    /// An item already in the set is moved from its lowest score to the new score, and an
+    /// item not yet in the set is inserted at the new score. Updates whose score is outside the
+    /// set's score bounds, or already held by another item in a set created with
+    /// `with_unique_scores`, are skipped.
+    /// Returns the number of updates applied.
+    pub fn set_scores(&self, updates: &[(T, S)]) -> usize
+    where
+        T: PartialEq + Clone,
+    {
+        let mut inner = lock::write(&self.inner);
+        let mut applied = 0;
+        for (item, new_score) in updates {
+            if !self.in_bounds(*new_score) {
+                continue;
+            }
+            let found = locate(&inner, item);
+            if found.is_none_or(|(score, _)| score != *new_score)
+                && self.is_taken(&inner, *new_score)
+            {
+                continue;
+            }
+            let item = match found {
+                Some((score, pos)) => self.take_locked(&mut inner, score, pos),
+                None => item.clone(),
+            };
+            self.push_locked(&mut inner, *new_score, item);
+            applied += 1;
+        }
+        applied
+    }
+
+    /// Returns each score with the half-open range of zero-based global ranks its items occupy,
+    /// as `(score, start_rank, end_rank)` in ascending order of score.
+    pub fn tier_rank_ranges(&self) -> Vec<(S, usize, usize)> {
+        let inner = lock::read(&self.inner);
+        let mut start = 0;
+        inner
+            .iter()
+            .map(|(&score, items)| {
+                let end = start + items.len();
+                let range = (score, start, end);
+                start = end;
+                range
+            })
+            .collect()
+    }
+
+    /// Removes a specified item only if it is currently stored at `expected_score`, checking
+    /// and removing under a single write lock. If several equal items share that score, only
+    /// the first is removed. The score is removed from the set once its last item is gone.
+    /// Returns `true` if the item was removed.
+    pub fn remove_if_score(&self, item: &T, expected_score: S) -> bool
+    where
+        T: PartialEq,
+    {
+        let mut inner = lock::write(&self.inner);
+        let pos = match inner.get(&expected_score) {
+            Some(items) => items.iter().position(|current_item| current_item == item),
+            None => None,
+        };
+        match pos {
+            Some(pos) => {
+                self.take_locked(&mut inner, expected_score, pos);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns every item with its dense rank, as `(rank, score, item)` in ascending order of
+    /// score. Items sharing a score share a rank, and ranks start at 1 and increase by one per
+    /// distinct score (1, 1, 2 rather than 1, 1, 3), like SQL's `DENSE_RANK()`.
+    pub fn dense_ranked_items(&self) -> Vec<(usize, S, T)>
+    where
+        T: Clone,
+    {
+        let inner = lock::read(&self.inner);
+        inner
+            .iter()
+            .enumerate()
+            .flat_map(|(i, (&score, items))| items.iter().map(move |item| (i + 1, score, item)))
+            .map(|(rank, score, item)| (rank, score, item.clone()))
+            .collect()
+    }
+
+    /// Returns every item with its dense rank counted from the highest score, as
+    /// `(rank, score, item)` in descending order of score.
+    /// This is the exact reverse of `dense_ranked_items`, with ranks assigned from the top.
+    pub fn dense_ranked_items_desc(&self) -> Vec<(usize, S, T)>
+    where
+        T: Clone,
+    {
+        let inner = lock::read(&self.inner);
+        inner
+            .iter()
+            .rev()
+            .enumerate()
+            .flat_map(|(i, (&score, items))| {
+                items.iter().rev().map(move |item| (i + 1, score, item))
+            })
+            .map(|(rank, score, item)| (rank, score, item.clone()))
+            .collect()
+    }
+
+    /// Returns the items inserted after `version`, as `(score, item)` pairs in ascending order
+    /// of score, for incrementally syncing from a previously seen `version()`.
+    /// An item counts as inserted whenever it is placed at a score, so an item moved to a new
+    /// score (for example by `update_score`) is reported again, as is an item that was removed
+    /// and then added back. Removed items are never reported.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the set was not created with `with_insertion_tracking`.
+    pub fn items_since(&self, version: u64) -> Vec<(S, T)>
+    where
+        T: Clone,
+    {
+        let inner = lock::read(&self.inner);
+        let stamps = self
+            .stamps
+            .as_ref()
+            .map(lock::lock)
+            .expect("items_since requires a set created with_insertion_tracking");
+        let mut since = Vec::new();
+        for (&score, items) in inner.iter() {
+            let Some(bucket) = stamps.get(&score) else {
+                continue;
+            };
+            for (item, &stamp) in items.iter().zip(bucket) {
+                if stamp > version {
+                    since.push((score, item.clone()));
+                }
+            }
+        }
+        since
+    }
+
+    /// Splits the items, in ascending order of score, into `tier_count` contiguous groups of
+    /// `(score, item)` pairs whose sizes differ by at most one, for assigning items to leagues.
+    /// Exactly `tier_count` groups are always returned, so some are empty when there are
+    /// fewer items than tiers; a `tier_count` of zero returns no groups.
+    /// Items sharing a score may be split across neighbouring groups.
+    pub fn bucketize_into_tiers(&self, tier_count: usize) -> Vec<Vec<(S, T)>>
+    where
+        T: Clone,
+    {
+        let inner = lock::read(&self.inner);
+        let total: usize = inner.values().map(Vec::len).sum();
+        let mut flat = inner
+            .iter()
+            .flat_map(|(&score, items)| items.iter().map(move |item| (score, item.clone())));
+        (0..tier_count)
+            .map(|i| {
+                // Boundaries at i * total / tier_count spread the remainder evenly
+                let size = (i + 1) * total / tier_count - i * total / tier_count;
+                flat.by_ref().take(size).collect()
+            })
+            .collect()
+    }
+
+    /// Returns the largest item under each score according to `T`'s own ordering, as
+    /// `(score, item)` pairs in ascending order of score. If several items in a score are
+    /// equally large, the last inserted one is returned.
+    pub fn max_item_per_score(&self) -> Vec<(S, T)>
+    where
+        T: Ord + Clone,
+    {
+        let inner = lock::read(&self.inner);
+        inner
+            .iter()
+            .filter_map(|(&score, items)| items.iter().max().map(|item| (score, item.clone())))
+            .collect()
+    }
+
+    /// Returns the lowest score holding a specified item, or `None` if it is not in the set.
+    pub fn score_of(&self, item: &T) -> Option<S>
+    where
+        T: PartialEq,
+    {
+        let inner = lock::read(&self.inner);
+        locate(&inner, item).map(|(score, _)| score)
+    }
+
+    /// Returns the score of the median item together with every item sharing that score, or
+    /// `None` if the set is empty. Items are ranked in ascending order of score; with an even
+    /// number of items the lower median is used, i.e. the item at zero-based rank
+    /// `(len - 1) / 2`.
+    pub fn median_tier(&self) -> Option<(S, Vec<T>)>
+    where
+        T: Clone,
+    {
+        let inner = lock::read(&self.inner);
+        let total: usize = inner.values().map(Vec::len).sum();
+        let mut remaining = total.checked_sub(1)? / 2;
+        for (&score, items) in inner.iter() {
+            if remaining < items.len() {
+                return Some((score, items.clone()));
+            }
+            remaining -= items.len();
+        }
+        None
+    }
+
+    /// Returns the contents as two parallel vectors `(scores, items)` in ascending order of
+    /// score, for columnar consumers and FFI. Both vectors have the same length, and index `i`
+    /// in each refers to the same entry. Items sharing a score keep their insertion order.
+    pub fn to_columns(&self) -> (Vec<S>, Vec<T>)
+    where
+        T: Clone,
+    {
+        let inner = lock::read(&self.inner);
+        let total = self.len.load(AtomicOrdering::Relaxed);
+        let mut scores = Vec::with_capacity(total);
+        let mut column = Vec::with_capacity(total);
+        for (&score, items) in inner.iter() {
+            scores.extend(std::iter::repeat_n(score, items.len()));
+            column.extend(items.iter().cloned());
+        }
+        (scores, column)
+    }
+
+    /// Removes every score, with all of its items, for which `f` returns `false`.
+    /// The predicate is given each score and the number of items it holds.
+    pub fn retain_scores<F>(&self, mut f: F)
+    where
+        F: FnMut(S, usize) -> bool,
+    {
+        let mut inner = lock::write(&self.inner);
+        let dropped: Vec<S> = inner
+            .iter()
+            .filter(|(&score, items)| !f(score, items.len()))
+            .map(|(&score, _)| score)
+            .collect();
+        for score in dropped {
+            self.take_bucket_locked(&mut inner, score);
+        }
+    }
+
+    /// Removes every item for which `f` returns `false`, in a single pass under one write lock.
+    /// The predicate is given each item with its score, in ascending order. Scores left with
+    /// no items are removed.
+    pub fn retain<F>(&self, mut f: F)
+    where
+        F: FnMut(S, &T) -> bool,
+    {
+        let mut inner = lock::write(&self.inner);
+        let scores: Vec<S> = inner.keys().copied().collect();
+        for score in scores {
+            self.extract_locked(&mut inner, score, |item| !f(score, item));
+        }
+    }
+
+    /// Compares the items of two sets by value, ignoring scores, and returns
+    /// `(only_in_self, only_in_other, in_both)`. Each distinct item appears once, ordered by
+    /// its first occurrence in ascending order of score in the set it is taken from (`self`
+    /// for `in_both`).
+    pub fn membership_diff(&self, other: &Self) -> (Vec<T>, Vec<T>, Vec<T>)
+    where
+        T: PartialEq + Clone,
+    {
+        let (ours_guard, theirs_guard) = read_pair(self, other);
+        let ours = distinct_items(&ours_guard);
+        let theirs = distinct_items(theirs_guard.as_deref().unwrap_or(&ours_guard));
+        let mut only_in_self = Vec::new();
+        let mut in_both = Vec::new();
+        for item in &ours {
+            if theirs.contains(item) {
+                in_both.push((*item).clone());
+            } else {
+                only_in_self.push((*item).clone());
+            }
+        }
+        let only_in_other = theirs
+            .into_iter()
+            .filter(|item| !ours.contains(item))
+            .cloned()
+            .collect();
+        (only_in_self, only_in_other, in_both)
+    }
+
+    /// Exchanges all the items between two scores under a single write lock.
+    /// Returns `true` if both scores exist; otherwise nothing changes and `false` is returned.
+    /// Swapping a score with itself changes nothing and returns `true` if the score exists.
+    pub fn swap_buckets(&self, score_a: S, score_b: S) -> bool {
+        let mut inner = lock::write(&self.inner);
+        if !inner.contains_key(&score_a) || !inner.contains_key(&score_b) {
+            return false;
+        }
+        if score_a == score_b {
+            return true;
+        }
+        let items_a = self.take_bucket_locked(&mut inner, score_a).unwrap();
+        let items_b = self.take_bucket_locked(&mut inner, score_b).unwrap();
+        self.append_bucket_locked(&mut inner, score_a, items_b);
+        self.append_bucket_locked(&mut inner, score_b, items_a);
+        true
+    }
+
+    /// Prepares for a burst of insertions by making room for `expected_count` items at each
+    /// listed score, as `(score, expected_count)` pairs.
+    /// Scores that already exist have their capacity grown to at least `expected_count`.
+    /// To keep the rule that no score is ever empty, scores that do not exist yet are not
+    /// created; instead their capacity is reserved and applied when their first item arrives.
+    /// Scores outside the set's score bounds are ignored.
+    pub fn prepare_scores(&self, scores: &[(S, usize)]) {
+        let mut inner = lock::write(&self.inner);
+        self.reserved.get_or_init(Default::default);
+        let mut reserved = self.reservations().unwrap();
+        for &(score, expected_count) in scores {
+            if !self.in_bounds(score) {
+                continue;
+            }
+            match inner.get_mut(&score) {
+                Some(items) => items.reserve(expected_count.saturating_sub(items.len())),
+                None => {
+                    let capacity = reserved.entry(score).or_default();
+                    *capacity = (*capacity).max(expected_count);
+                }
+            }
+        }
+    }
+
+    /// Calls `f` with each listed score and a borrowed slice of its items, in the order given
+    /// by `order`, skipping scores that are not in the set. A read lock is held for the whole
+    /// traversal, so `f` must not modify the set.
+    pub fn for_each_in_order<F>(&self, order: &[S], mut f: F)
+    where
+        F: FnMut(S, &[T]),
+    {
+        let inner = lock::read(&self.inner);
+        for &score in order {
+            if let Some(items) = inner.get(&score) {
+                f(score, items);
+            }
+        }
+    }
+
+    /// Returns how many other items share a specified item's score, or `None` if the item is
+    /// not in the set. An item stored under several scores is judged by its lowest score.
+    pub fn tie_count(&self, item: &T) -> Option<usize>
+    where
+        T: PartialEq,
+    {
+        let inner = lock::read(&self.inner);
+        let (score, _) = locate(&inner, item)?;
+        Some(inner[&score].len() - 1)
+    }
+
+    /// Returns a checksum of the set's contents, computed over the `(score, item)` pairs in
+    /// ascending order of score. Sets with identical contents produce the same checksum, and
+    /// differing sets almost certainly do not. Because items sharing a score are hashed in
+    /// insertion order, reordering tied items changes the checksum.
+    /// The checksum is deterministic across processes built with the same Rust version and
+    /// target, but is not guaranteed to stay the same across Rust releases or platforms.
+    pub fn content_hash(&self) -> u64
+    where
+        T: Hash,
+        S: Hash,
+    {
+        let inner = lock::read(&self.inner);
+        let mut hasher = DefaultHasher::new(); // Fixed keys, unlike RandomState
+        let mut count = 0usize;
+        for (score, items) in inner.iter() {
+            for item in items {
+                score.hash(&mut hasher);
+                item.hash(&mut hasher);
+                count += 1;
+            }
+        }
+        count.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Returns the best (lowest) zero-based rank, in ascending order of score, reached by any
+    /// item in each group produced by `key_fn`. Ranks are positional, so tied items hold
+    /// distinct ranks in insertion order and a group's best rank among ties is that of its
+    /// earliest-inserted member.
+    pub fn group_best_rank<K, F>(&self, key_fn: F) -> HashMap<K, usize>
+    where
+        K: Hash + Eq,
+        F: Fn(&T) -> K,
+    {
+        let inner = lock::read(&self.inner);
+        let mut best = HashMap::new();
+        for (rank, item) in inner.values().flatten().enumerate() {
+            best.entry(key_fn(item)).or_insert(rank); // The first rank seen is the lowest
+        }
+        best
+    }
+
+    /// Returns `true` if a specified item is stored under any score.
+    /// This scans every score; use `contains_at` when the score is known.
+    pub fn contains(&self, item: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        let inner = lock::read(&self.inner);
+        inner.values().any(|items| items.contains(item))
+    }
+
+    /// Returns `true` if a specified item is stored under the given score.
+    pub fn contains_at(&self, score: S, item: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        let inner = lock::read(&self.inner);
+        inner.get(&score).is_some_and(|items| items.contains(item))
+    }
+
+    /// Returns each score in ascending order paired with the zero-based global rank of its
+    /// first item, i.e. the number of items with a lower score. The result is sorted by both
+    /// fields, so clients can binary-search it to resolve a score to a rank range.
+    pub fn score_index(&self) -> Vec<(S, usize)> {
+        let inner = lock::read(&self.inner);
+        let mut offset = 0;
+        inner
+            .iter()
+            .map(|(&score, items)| {
+                let entry = (score, offset);
+                offset += items.len();
+                entry
+            })
+            .collect()
+    }
+
+    /// Builds a new, independent set by passing every item and its score to `f`, which returns
+    /// the item's score in the new set or `None` to leave it out. Items keep their relative
+    /// order, and the new set has the same configuration as this one, so items mapped outside
+    /// its score bounds are left out too. In a set created with `with_unique_scores`, an item
+    /// mapped to a score already given to an earlier item is left out.
+    pub fn map_rescore<F>(&self, f: F) -> Self
+    where
+        T: Clone,
+        F: Fn(S, &T) -> Option<S>,
+    {
+        let inner = lock::read(&self.inner);
+        let mut mapped: BTreeMap<S, Vec<T>> = BTreeMap::new();
+        for (&score, items) in inner.iter() {
+            for item in items {
+                if let Some(new_score) = f(score, item).filter(|&s| self.in_bounds(s)) {
+                    if self.is_taken(&mapped, new_score) {
+                        continue;
+                    }
+                    mapped.entry(new_score).or_default().push(item.clone());
+                }
+            }
+        }
+        drop(inner);
+        self.new_like(mapped)
+    }
+
+    /// Returns the score and item count of the `k`-th largest bucket (zero-based, so `k == 0`
+    /// is the most crowded score), breaking size ties by the lower score.
+    /// Returns `None` if the set has `k` or fewer distinct scores.
+    pub fn nth_largest_bucket(&self, k: usize) -> Option<(S, usize)> {
+        let inner = lock::read(&self.inner);
+        let mut buckets: Vec<(S, usize)> = inner
+            .iter()
+            .map(|(&score, items)| (score, items.len()))
+            .collect();
+        drop(inner);
+        if k >= buckets.len() {
+            return None;
+        }
+        let (_, nth, _) =
+            buckets.select_nth_unstable_by_key(k, |&(score, count)| (Reverse(count), score));
+        Some(*nth)
+    }
+
+    /// Removes and returns the item at the top of the set: the most recently added item with
+    /// the highest score. Returns `None` if the set is empty.
+    /// The item is found and removed under a single write lock, so concurrent callers never
+    /// receive the same item.
+    pub fn pop_highest(&self) -> Option<(S, T)> {
+        let mut inner = lock::write(&self.inner);
+        let (&score, items) = inner.last_key_value()?;
+        let pos = items.len() - 1;
+        Some((score, self.take_locked(&mut inner, score, pos)))
+    }
+
+    /// Removes and returns the item at the bottom of the set: the earliest added item with the
+    /// lowest score. Returns `None` if the set is empty.
+    /// The item is found and removed under a single write lock, so concurrent callers never
+    /// receive the same item.
+    pub fn pop_lowest(&self) -> Option<(S, T)> {
+        let mut inner = lock::write(&self.inner);
+        let score = *inner.first_key_value()?.0;
+        Some((score, self.take_locked(&mut inner, score, 0)))
+    }
+
+    /// Returns every item as a `(score, item)` pair in ascending order, together with the
+    /// version of the set those contents belong to. Both are read under a single lock, so
+    /// callers can cache the contents keyed by the version and skip re-fetching while
+    /// `version` still returns the same value.
+    pub fn snapshot_with_version(&self) -> (Vec<(S, T)>, u64)
+    where
+        T: Clone,
+    {
+        let inner = lock::read(&self.inner);
+        let items = inner
+            .iter()
+            .flat_map(|(&score, items)| items.iter().map(move |item| (score, item.clone())))
+            .collect();
+        (items, self.version()) // Writers bump the version while holding the write lock
+    }
+
+    /// Returns the zero-based position of `item` in ascending order: the number of items with
+    /// a lower score plus the item's position among the items sharing its score.
+    /// If the item appears under several scores, its lowest occurrence is used.
+    /// Returns `None` if the item is not in the set.
+    pub fn rank(&self, item: &T) -> Option<usize>
+    where
+        T: PartialEq,
+    {
+        let inner = lock::read(&self.inner);
+        rank_of(&inner, item)
+    }
+
+    /// Returns the zero-based position of `item` in descending order, so the item at the top
+    /// of the set has a reverse rank of 0. This is always `len() - 1 - rank(item)`.
+    /// Returns `None` if the item is not in the set.
+    pub fn reverse_rank(&self, item: &T) -> Option<usize>
+    where
+        T: PartialEq,
+    {
+        let inner = lock::read(&self.inner);
+        let rank = rank_of(&inner, item)?;
+        let total: usize = inner.values().map(Vec::len).sum();
+        Some(total - 1 - rank)
+    }
+
+    /// Clears `buf` and fills it with every item as a `(rank, score, item)` triple in
+    /// ascending order, where `rank` is the item's zero-based position as returned by `rank`.
+    /// Reusing the same buffer across calls avoids allocating once it has grown large enough.
+    pub fn collect_ranked_into(&self, buf: &mut Vec<(usize, S, T)>)
+    where
+        T: Clone,
+    {
+        buf.clear();
+        let inner = lock::read(&self.inner);
+        buf.reserve(inner.values().map(Vec::len).sum());
+        let items = inner
+            .iter()
+            .flat_map(|(&score, items)| items.iter().map(move |item| (score, item)));
+        for (rank, (score, item)) in items.enumerate() {
+            buf.push((rank, score, item.clone()));
+        }
+    }
+
+    /// Clears `buf` and fills it with every item as a `(rank, score, item)` triple in
+    /// descending order, where `rank` is the item's zero-based position as returned by
+    /// `reverse_rank`. This is the exact reverse of `collect_ranked_into`.
+    pub fn collect_ranked_into_desc(&self, buf: &mut Vec<(usize, S, T)>)
+    where
+        T: Clone,
+    {
+        buf.clear();
+        let inner = lock::read(&self.inner);
+        buf.reserve(inner.values().map(Vec::len).sum());
+        let items = inner
+            .iter()
+            .rev()
+            .flat_map(|(&score, items)| items.iter().rev().map(move |item| (score, item)));
+        for (rank, (score, item)) in items.enumerate() {
+            buf.push((rank, score, item.clone()));
+        }
+    }
+
+    /// Returns an iterator over a snapshot of every item as a `(score, item)` pair, in
+    /// ascending order of score, with items sharing a score in insertion order.
+    /// The snapshot is cloned under a single read lock, so the lock is not held while
+    /// iterating and later changes to the set are not reflected.
+    pub fn iter(&self) -> impl Iterator<Item = (S, T)>
+    where
+        T: Clone,
+    {
+        self.snapshot_with_version().0.into_iter()
+    }
+
+    /// Returns every item as a `(score, item)` pair, in ascending order of score, with items
+    /// sharing a score in insertion order. The items are cloned under a single read lock.
+    pub fn to_sorted_vec(&self) -> Vec<(S, T)>
+    where
+        T: Clone,
+    {
+        self.snapshot_with_version().0
+    }
+
+    /// Returns every item as a `(score, item)` pair in descending order: the exact reverse of
+    /// `to_sorted_vec`, so items sharing a score come most recently added first.
+    pub fn to_sorted_vec_desc(&self) -> Vec<(S, T)>
+    where
+        T: Clone,
+    {
+        let mut items = self.to_sorted_vec();
+        items.reverse();
+        items
+    }
+
+    /// Removes every item from the set, keeping its configuration, so a long-lived set can be
+    /// reused. Capacity reserved with `prepare_scores` is released as well. The version keeps
+    /// increasing rather than being reset, so cached results are never mistaken for current.
+    pub fn clear(&self) {
+        let mut inner = lock::write(&self.inner);
+        let scores: Vec<S> = inner.keys().copied().collect();
+        for score in scores {
+            self.take_bucket_locked(&mut inner, score);
+        }
+        if let Some(mut reserved) = self.reservations() {
+            reserved.clear();
+        }
+    }
+
+    /// Removes every item from the set under a single write lock and returns them as
+    /// `(score, item)` pairs, in ascending order of score, with items sharing a score in
+    /// insertion order. Items are moved out rather than cloned, unlike `snapshot` followed by
+    /// `clear`. The set keeps its configuration, and, as with `clear`, capacity reserved with
+    /// `prepare_scores` is released.
+    pub fn drain(&self) -> Vec<(S, T)> {
+        let mut inner = lock::write(&self.inner);
+        let scores: Vec<S> = inner.keys().copied().collect();
+        let mut drained = Vec::with_capacity(self.len());
+        for score in scores {
+            let items = self.take_bucket_locked(&mut inner, score).unwrap();
+            drained.extend(items.into_iter().map(|item| (score, item)));
+        }
+        if let Some(mut reserved) = self.reservations() {
+            reserved.clear();
+        }
+        drained
+    }
+
+    /// Passes the items for `score` to `f` under a single write lock, for edits local to one
+    /// score such as sorting, deduplicating, or reordering, and returns `f`'s result.
+    /// Returns `None` without calling `f` if the score does not exist. If `f` leaves the
+    /// vector empty, the score is removed.
+    ///
+    /// In a set created with `with_unique_scores`, `f` must leave at most one item. If it
+    /// leaves more, the items are restored to how they were before `f` ran, and `None` is
+    /// returned in place of `f`'s result.
+    ///
+    /// The items are edited in place, so if `f` panics the items stay as `f` left them, and
+    /// the length and cached sum are still brought in step. In a set created with
+    /// `with_insertion_tracking`, an item keeps its insertion record if an equal item was
+    /// there before `f` ran, and otherwise counts as newly inserted. To match them up, the
+    /// items are cloned before `f` runs in such a set, or in a set with unique scores.
+    pub fn modify_bucket<R, F>(&self, score: S, f: F) -> Option<R>
+    where
+        T: PartialEq + Clone,
+        F: FnOnce(&mut Vec<T>) -> R,
+    {
+        let mut inner = lock::write(&self.inner);
+        let items = inner.get(&score)?;
+        let keep = self.unique_scores || self.stamps.is_some();
+        let mut settle = SettleBucket {
+            set: self,
+            score,
+            before: items.len(),
+            original: keep.then(|| items.clone()),
+            restored: false,
+            inner: &mut inner,
+        };
+        let result = f(settle.inner.get_mut(&score).unwrap());
+        (!settle.restore_if_tied()).then_some(result)
+    }
+
+    /// Moves every item at `old_score` to `new_score`, appending them in their existing order
+    /// after any items already there. Returns `true` if any items were moved.
+    /// Nothing is moved if `old_score` equals `new_score` or holds no items, if `new_score` is
+    /// outside the set's score bounds, or if the set has unique scores and `new_score` is
+    /// already taken.
+    pub fn move_score(&self, old_score: S, new_score: S) -> bool {
+        if old_score == new_score || !self.in_bounds(new_score) {
+            return false;
+        }
+        let mut inner = lock::write(&self.inner);
+        if self.is_taken(&inner, new_score) {
+            return false;
+        }
+        match self.take_bucket_locked(&mut inner, old_score) {
+            Some(items) => {
+                self.append_bucket_locked(&mut inner, new_score, items);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes a specified item without knowing its score, and returns the score it was
+    /// removed from, or `None` if the item is not in the set.
+    /// Only one copy is removed: if the item is stored under several scores, the lowest-score
+    /// occurrence goes, mirroring `score_of`, and among equal items at that score the earliest
+    /// added goes.
+    pub fn remove_item(&self, item: &T) -> Option<S>
+    where
+        T: PartialEq,
+    {
+        let mut inner = lock::write(&self.inner);
+        let (score, pos) = locate(&inner, item)?;
+        self.take_locked(&mut inner, score, pos);
+        Some(score)
+    }
+
+    /// Adds an item with a given score only if an equal item is not already stored under any
+    /// score, so each item appears at most once.
+    /// Returns `true` if the item was inserted, and `false` without changing anything if it was
+    /// already present or `add` would reject the score.
+    pub fn add_unique(&self, score: S, item: T) -> bool
+    where
+        T: PartialEq,
+    {
+        if !self.in_bounds(score) {
+            return false;
+        }
+        let mut inner = lock::write(&self.inner);
+        if self.is_taken(&inner, score) || locate(&inner, &item).is_some() {
+            return false;
+        }
+        self.push_locked(&mut inner, score, item);
+        true
+    }
+
+    /// Inserts an item at a given score, or moves it there if an equal item is already in the
+    /// set, replacing the stored value with `item`. The item goes to the end of the items at
+    /// its new score. An item stored under several scores is moved from its lowest score.
+    /// Returns `false` without changing anything if `add` would reject the score.
+    pub fn add_or_update(&self, score: S, item: T) -> bool
+    where
+        T: PartialEq,
+    {
+        if !self.in_bounds(score) {
+            return false;
+        }
+        let mut inner = lock::write(&self.inner);
+        let found = locate(&inner, &item);
+        if found.is_none_or(|(old_score, _)| old_score != score) && self.is_taken(&inner, score) {
+            return false;
+        }
+        if let Some((old_score, pos)) = found {
+            self.take_locked(&mut inner, old_score, pos);
+        }
+        self.push_locked(&mut inner, score, item);
+        true
+    }
+
+    /// Returns a new set holding every item from both sets, with items at equal scores merged
+    /// so this set's items come before `other`'s. An item present in both sets is kept twice,
+    /// even at different scores. The new set has this set's configuration, so items from
+    /// `other` that it would reject, such as scores outside its bounds, are left out. If this
+    /// set has unique scores, only the earliest added of `other`'s items at a free score is
+    /// kept. Both sets are read-locked together, so the result is a consistent view of each.
+    pub fn union(&self, other: &Self) -> Self
+    where
+        T: Clone,
+    {
+        let (ours, theirs) = read_pair(self, other);
+        let mut merged = ours.clone();
+        for (&score, items) in theirs.as_deref().unwrap_or(&ours) {
+            if !self.in_bounds(score) || self.is_taken(&merged, score) {
+                continue;
+            }
+            let keep = if self.unique_scores { 1 } else { items.len() };
+            merged
+                .entry(score)
+                .or_default()
+                .extend(items.iter().take(keep).cloned());
+        }
+        drop((ours, theirs));
+        self.new_like(merged)
+    }
+
+    /// Returns a new set holding only the items of this set that also appear, at any score, in
+    /// `other`, each kept at its score in this set and in its current order. The new set has
+    /// this set's configuration. Both sets are read-locked together.
+    /// Each item is looked up by scanning `other`, so this takes O(n·m) time.
+    pub fn intersection_by_item(&self, other: &Self) -> Self
+    where
+        T: PartialEq + Clone,
+    {
+        let (ours, theirs) = read_pair(self, other);
+        let theirs = distinct_items(theirs.as_deref().unwrap_or(&ours));
+        let mut common: BTreeMap<S, Vec<T>> = BTreeMap::new();
+        for (&score, items) in ours.iter() {
+            let kept: Vec<T> = items
+                .iter()
+                .filter(|item| theirs.contains(item))
+                .cloned()
+                .collect();
+            if !kept.is_empty() {
+                common.insert(score, kept);
+            }
+        }
+        drop(ours);
+        self.new_like(common)
+    }
+
+    /// Returns a point-in-time copy of the whole score map, cloned under a single read lock, so
+    /// it can be iterated, serialized, or compared without holding the lock.
+    /// This clones every item, which may be expensive for large sets.
+    pub fn snapshot(&self) -> BTreeMap<S, Vec<T>>
+    where
+        T: Clone,
+    {
+        lock::read(&self.inner).clone()
+    }
+
+    /// Returns the item at zero-based position `rank` in ascending order, the inverse of
+    /// `rank`, as a `(score, item)` pair. Returns `None` if `rank` is not less than `len()`.
+    pub fn get_by_rank(&self, rank: usize) -> Option<(S, T)>
+    where
+        T: Clone,
+    {
+        let inner = lock::read(&self.inner);
+        item_at(&inner, rank)
+    }
+
+    /// Returns the item at zero-based position `rank` in descending order, the inverse of
+    /// `reverse_rank`, as a `(score, item)` pair. Returns `None` if `rank` is not less than
+    /// `len()`.
+    pub fn get_by_reverse_rank(&self, rank: usize) -> Option<(S, T)>
+    where
+        T: Clone,
+    {
+        let inner = lock::read(&self.inner);
+        let total: usize = inner.values().map(Vec::len).sum();
+        item_at(&inner, total.checked_sub(rank)?.checked_sub(1)?)
+    }
+
+    /// Removes every item with a score within `[min, max]` under a single write lock, like
+    /// Redis `ZREMRANGEBYSCORE`, and returns how many items were removed.
+    /// Returns 0 without changing anything if `min > max`.
+    pub fn remove_range_by_score(&self, min: S, max: S) -> usize {
+        if min > max {
+            return 0; // BTreeMap::range panics on inverted bounds
+        }
+        let mut inner = lock::write(&self.inner);
+        let scores: Vec<S> = inner.range(min..=max).map(|(&score, _)| score).collect();
+        scores
+            .into_iter()
+            .filter_map(|score| self.take_bucket_locked(&mut inner, score))
+            .map(|items| items.len())
+            .sum()
+    }
+
+    /// Removes and returns up to `n` items from the top of the set, in descending order, as
+    /// `pop_highest` called `n` times would. All items are taken under a single write lock, so
+    /// concurrent callers receive disjoint items.
+    pub fn pop_n_highest(&self, n: usize) -> Vec<(S, T)> {
+        let mut inner = lock::write(&self.inner);
+        let mut popped = Vec::new();
+        while popped.len() < n {
+            let Some((&score, items)) = inner.last_key_value() else {
+                break;
+            };
+            let keep = items.len().saturating_sub(n - popped.len());
+            let mut pos = 0;
+            let taken = self.extract_locked(&mut inner, score, |_| {
+                pos += 1;
+                pos > keep
+            });
+            popped.extend(taken.into_iter().rev().map(|item| (score, item)));
+        }
+        popped
+    }
+
+    /// Removes and returns up to `n` items from the bottom of the set, in ascending order, as
+    /// `pop_lowest` called `n` times would. All items are taken under a single write lock, so
+    /// concurrent callers receive disjoint items.
+    pub fn pop_n_lowest(&self, n: usize) -> Vec<(S, T)> {
+        let mut inner = lock::write(&self.inner);
+        let mut popped = Vec::new();
+        while popped.len() < n {
+            let Some(&score) = inner.keys().next() else {
+                break;
+            };
+            let mut wanted = n - popped.len();
+            let taken = self.extract_locked(&mut inner, score, |_| {
+                let take = wanted > 0;
+                wanted = wanted.saturating_sub(1);
+                take
+            });
+            popped.extend(taken.into_iter().map(|item| (score, item)));
+        }
+        popped
+    }
+
+    /// Returns the score at quantile `q` of the item scores, using the nearest-rank method: the
+    /// score of the item at position `ceil(q * len())` in ascending order, counting from 1, so
+    /// `q == 0.0` gives the lowest score and `q == 1.0` the highest. Scores shared by several
+    /// items weigh once per item.
+    /// Returns `None` if the set is empty, or if `q` is NaN or outside `[0.0, 1.0]`.
+    pub fn percentile(&self, q: f64) -> Option<S> {
+        if !(0.0..=1.0).contains(&q) {
+            return None; // Also rejects NaN, which is not contained in any range
+        }
+        let inner = lock::read(&self.inner);
+        let total: usize = inner.values().map(Vec::len).sum();
+        if total == 0 {
+            return None;
+        }
+        let exact = q * total as f64;
+        // Snap products that floating-point error nudged off a whole number, such as
+        // 0.07 * 100.0 == 7.000000000000001, so that ceil does not push the rank up by one
+        let nearest = exact.round();
+        let exact = if (exact - nearest).abs() <= 1e-9 * nearest.max(1.0) {
+            nearest
+        } else {
+            exact
+        };
+        let rank = (exact.ceil() as usize).clamp(1, total);
+        let mut seen = 0;
+        for (&score, items) in inner.iter() {
+            seen += items.len();
+            if seen >= rank {
+                return Some(score);
+            }
+        }
+        None
+    }
+
+    /// Adds every `(score, item)` pair under a single write lock, appending items to each score
+    /// in iteration order, and returns how many were added. Pairs that `add` would reject, such
+    /// as scores outside the set's score bounds, are skipped. This is the inherent counterpart
+    /// of the `Extend` implementation, usable through a shared reference.
+    pub fn add_many<I>(&self, items: I) -> usize
+    where
+        I: IntoIterator<Item = (S, T)>,
+    {
+        let mut inner = lock::write(&self.inner);
+        let mut added = 0;
+        for (score, item) in items {
+            if self.in_bounds(score) && !self.is_taken(&inner, score) {
+                self.push_locked(&mut inner, score, item);
+                added += 1;
+            }
+        }
+        added
+    }
+
+    /// Removes each `(score, item)` pair under a single write lock, as `remove` called once per
+    /// pair would, and returns how many items were removed in total. Pairs not in the set
+    /// remove nothing, and scores left with no items are removed.
+    pub fn remove_many(&self, items: &[(S, T)]) -> usize
+    where
+        T: PartialEq,
+    {
+        let mut inner = lock::write(&self.inner);
+        items
+            .iter()
+            .map(|(score, item)| {
+                self.extract_locked(&mut inner, *score, |current_item| current_item == item)
+                    .len()
+            })
+            .sum()
+    }
+}
+
+impl<T> ScoredSortedSet<T> {
+    /// Creates a new, empty `ScoredSortedSet`.
+    pub fn new() -> Self {
+        Self::with_score_type()
+    }
+
+    /// Creates a new, empty `ScoredSortedSet` that caches the sum of all scores, so that
+    /// `sum_of_scores` and `mean_score` run in O(1).
+    /// Every mutation pays a small bookkeeping cost to keep the cached sum current.
+    pub fn with_cached_aggregates() -> Self {
+        ScoredSortedSet {
+            sum: Some((AtomicI64::new(0), i64::from)),
+            ..Self::new()
+        }
+    }
+
+    /// Returns the sum of the scores of all items. A score shared by several items contributes
+    /// once per item. Runs in O(1) for sets created with `with_cached_aggregates`.
+    pub fn sum_of_scores(&self) -> i64 {
+        if let Some((sum, _)) = &self.sum {
+            return sum.load(AtomicOrdering::Relaxed);
+        }
+        let inner = lock::read(&self.inner);
+        sum_of(&inner, i64::from)
+    }
+
+    /// Returns the mean score across all items, or `None` if the set is empty.
+    /// Runs in O(1) for sets created with `with_cached_aggregates`.
+    pub fn mean_score(&self) -> Option<f64> {
+        let inner = lock::read(&self.inner); // Keeps the sum and count consistent
+        let sum = match &self.sum {
+            Some((sum, _)) => sum.load(AtomicOrdering::Relaxed),
+            None => sum_of(&inner, i64::from),
+        };
+        let count = self.len.load(AtomicOrdering::Relaxed);
+        if count == 0 {
+            return None;
+        }
+        Some(sum as f64 / count as f64)
+    }
+
+    /// Returns how many items fall into each band of `bucket_width` consecutive scores, as
+    /// `(band_start, count)` pairs in ascending order. Only populated bands are included.
+    /// Bands start at multiples of `bucket_width`, except that a band reaching below `i32::MIN`
+    /// is reported as starting at `i32::MIN`. No items are read or cloned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bucket_width` is not positive.
+    pub fn anonymized_distribution(&self, bucket_width: i32) -> Vec<(i32, usize)> {
+        assert!(bucket_width > 0, "bucket_width must be positive");
+        let inner = lock::read(&self.inner);
+        let mut bands: Vec<(i32, usize)> = Vec::new();
+        for (&score, items) in inner.iter() {
+            let start = score as i64 - (score as i64).rem_euclid(bucket_width as i64);
+            let start = start.max(i32::MIN as i64) as i32;
+            match bands.last_mut() {
+                Some((last_start, count)) if *last_start == start => *count += items.len(),
+                _ => bands.push((start, items.len())),
+            }
+        }
+        bands
+    }
+
+    /// Adds an item with a fractional score, stored in fixed point as thousandths.
+    /// The score is multiplied by 1000 and rounded to the nearest integer, with halfway cases
+    /// rounded away from zero, so it keeps three decimal places (12.3456 is stored as 12346
+    /// and reads back as 12.346). Representable scores run from -2147483.648 to 2147483.647.
+    /// Returns `false` without adding the item if the score is not finite, falls outside that
+    /// range, or is rejected by `add` (score bounds apply to the stored thousandths).
+    pub fn add_f64(&self, score: f64, item: T) -> bool {
+        let scaled = (score * FIXED_POINT_SCALE).round();
+        if !(i32::MIN as f64..=i32::MAX as f64).contains(&scaled) {
+            return false; // Also rejects NaN, which is not contained in any range
+        }
+        self.add(scaled as i32, item)
+    }
+
+    /// Returns the lowest score holding a specified item as a fractional score, reading the
+    /// stored integer as thousandths. This is the counterpart of `add_f64`.
+    pub fn score_of_f64(&self, item: &T) -> Option<f64>
+    where
+        T: PartialEq,
+    {
+        self.score_of(item)
+            .map(|score| score as f64 / FIXED_POINT_SCALE)
+    }
+
+    /// Returns the smallest score increase that would put `me` strictly above `target`, or
+    /// `Some(0)` if `me` is already ahead. Returns `None` if either item is not in the set.
+    /// Items stored under several scores are judged by their lowest score, and a result too
+    /// large for an `i32` saturates at `i32::MAX`.
+    pub fn points_to_overtake(&self, me: &T, target: &T) -> Option<i32>
+    where
+        T: PartialEq,
+    {
+        let inner = lock::read(&self.inner);
+        let (my_score, _) = locate(&inner, me)?;
+        let (target_score, _) = locate(&inner, target)?;
+        let needed = (target_score as i64 - my_score as i64 + 1).max(0);
+        Some(needed.min(i32::MAX as i64) as i32)
+    }
+
+    /// Returns the two adjacent populated scores with the widest gap between them, as
+    /// `(lower_score, higher_score, gap_width)` where `gap_width` is `higher_score - lower_score`,
+    /// saturating at `i32::MAX`. If several gaps are equally wide, the lowest one is returned.
+    /// Returns `None` if the set has fewer than two scores.
+    pub fn largest_score_gap(&self) -> Option<(i32, i32, i32)> {
+        let inner = lock::read(&self.inner);
+        let mut scores = inner.keys();
+        let mut lower = *scores.next()?;
+        let mut widest: Option<(i32, i32, i64)> = None;
+        for &higher in scores {
+            let width = higher as i64 - lower as i64; // i64 so that MIN..MAX cannot overflow
+            if widest.is_none_or(|(_, _, widest_width)| width > widest_width) {
+                widest = Some((lower, higher, width));
+            }
+            lower = higher;
+        }
+        widest.map(|(lower, higher, width)| (lower, higher, width.min(i32::MAX as i64) as i32))
+    }
+
+    /// Adds `delta` to the score of every item for which `predicate` returns `true`, under a
+    /// single write lock, and returns the number of items moved.
+    /// Moved items are appended to any items already at their new score, in ascending order of
+    /// their old score. Items whose new score would overflow an `i32` or fall outside the
+    /// set's score bounds are skipped and keep their current score. In a set created with
+    /// `with_unique_scores`, an item is also skipped if its new score would still be held by
+    /// another item once the adjustment is done. A `delta` of zero moves nothing and returns 0.
+    pub fn adjust_scores_where<P>(&self, predicate: P, delta: i32) -> usize
+    where
+        P: Fn(&T) -> bool,
+    {
+        if delta == 0 {
+            return 0; // Re-appending items in place would only reorder them
+        }
+        let mut inner = lock::write(&self.inner);
+        let scores: Vec<i32> = inner.keys().copied().collect();
+        let target = |score: i32| {
+            // Skip rather than clamp, so no two scores merge by accident
+            score.checked_add(delta).filter(|&s| self.in_bounds(s))
+        };
+
+        // With unique scores, a move is blocked if its target keeps its item. Deciding from the
+        // far end in the direction of `delta` settles each target before anything moves into it.
+        let mut moving = BTreeSet::new();
+        if self.unique_scores {
+            let mut order = scores.clone();
+            if delta > 0 {
+                order.reverse();
+            }
+            for score in order {
+                let Some(new_score) = target(score) else {
+                    continue;
+                };
+                let blocked = new_score != score
+                    && inner.contains_key(&new_score)
+                    && !moving.contains(&new_score);
+                if !blocked && inner[&score].iter().any(&predicate) {
+                    moving.insert(score);
+                }
+            }
+        }
+
+        let mut moved = Vec::new();
+        for score in scores {
+            let Some(new_score) = target(score) else {
+                continue;
+            };
+            if self.unique_scores && !moving.contains(&score) {
+                continue;
+            }
+            let items = self.extract_locked(&mut inner, score, &predicate);
+            moved.push((new_score, items));
+        }
+
+        // Insert only after extracting everything, so no item is adjusted twice
+        let mut count = 0;
+        for (new_score, items) in moved {
+            count += items.len();
+            self.append_bucket_locked(&mut inner, new_score, items);
+        }
+        count
+    }
+
+    /// Moves a specified item to one above the current highest score, making it the sole
+    /// leader, and returns its new score. An item that is already alone at the highest score
+    /// stays where it is. Returns `None` without changing anything if the item is not in the
+    /// set, or if the new score would overflow an `i32` or fall outside the set's score bounds.
+    /// An item stored under several scores is moved from its lowest score.
+    pub fn move_to_top(&self, item: &T) -> Option<i32>
+    where
+        T: PartialEq + Clone,
+    {
+        let mut inner = lock::write(&self.inner);
+        let (score, pos) = locate(&inner, item)?;
+        let (&max, top_items) = inner.last_key_value()?;
+        if score == max && top_items.len() == 1 {
+            return Some(score);
+        }
+        let new_score = max.checked_add(1).filter(|&s| self.in_bounds(s))?;
+        let item = self.take_locked(&mut inner, score, pos);
+        self.push_locked(&mut inner, new_score, item);
+        Some(new_score)
+    }
+
+    /// Moves a specified item to one below the current lowest score, making it the sole
+    /// lowest-ranked item, and returns its new score. This is the mirror of `move_to_top`,
+    /// with the same handling of items already in place, overflow, and score bounds.
+    pub fn move_to_bottom(&self, item: &T) -> Option<i32>
+    where
+        T: PartialEq + Clone,
+    {
+        let mut inner = lock::write(&self.inner);
+        let (score, pos) = locate(&inner, item)?;
+        let (&min, bottom_items) = inner.first_key_value()?;
+        if score == min && bottom_items.len() == 1 {
+            return Some(score);
+        }
+        let new_score = min.checked_sub(1).filter(|&s| self.in_bounds(s))?;
+        let item = self.take_locked(&mut inner, score, pos);
+        self.push_locked(&mut inner, new_score, item);
+        Some(new_score)
+    }
+
+    /// Adds `delta` to the score of a specified item under a single write lock, so concurrent
+    /// increments are never lost, and returns the new score. The item moves to the end of the
+    /// items at its new score. An item stored under several scores is moved from its lowest score.
+    /// Returns `None` without changing anything if the item is not in the set, if the new score
+    /// would overflow an `i32`, or if `add` would reject the new score.
+    pub fn increment_score(&self, item: &T, delta: i32) -> Option<i32>
+    where
+        T: PartialEq + Clone,
+    {
+        let mut inner = lock::write(&self.inner);
+        let (score, pos) = locate(&inner, item)?;
+        self.increment_locked(&mut inner, score, pos, delta)
+    }
+
+    /// Adds `delta` to the score of a specified item like `increment_score`, or inserts the
+    /// item at a score of `delta` if it is not in the set, and returns its new score.
+    /// Returns `None` without changing anything if the new score would overflow an `i32` or
+    /// `add` would reject it.
+    pub fn increment_or_insert(&self, item: T, delta: i32) -> Option<i32>
+    where
+        T: PartialEq,
+    {
+        let mut inner = lock::write(&self.inner);
+        match locate(&inner, &item) {
+            Some((score, pos)) => self.increment_locked(&mut inner, score, pos, delta),
+            None if self.in_bounds(delta) && !self.is_taken(&inner, delta) => {
+                self.push_locked(&mut inner, delta, item);
+                Some(delta)
+            }
+            None => None,
+        }
+    }
+
+    /// Moves the item at `pos` among the items for `score` up by `delta`, returning the new
+    /// score, or `None` if it would overflow or be rejected. Must be called with the write lock
+    /// held.
+    fn increment_locked(
+        &self,
+        inner: &mut BTreeMap<i32, Vec<T>>,
+        score: i32,
+        pos: usize,
+        delta: i32,
+    ) -> Option<i32> {
+        let new_score = score.checked_add(delta).filter(|&s| self.in_bounds(s))?;
+        if new_score != score && self.is_taken(inner, new_score) {
+            return None;
+        }
+        let item = self.take_locked(inner, score, pos);
+        self.push_locked(inner, new_score, item);
+        Some(new_score)
+    }
+
+    /// Combines several sets into a new one, like Redis `ZUNIONSTORE` with weights: each
+    /// distinct item's score is the sum of `score * weight` over every `(set, weight)` input
+    /// it appears in, counting each occurrence if an input stores it under several scores.
+    /// Sums beyond the range of an `i32` saturate at `i32::MIN` or `i32::MAX`. Items sharing a
+    /// resulting score are ordered by first appearance across the inputs. Inputs are read one
+    /// at a time, and the new set has no score bounds or other options enabled.
+    pub fn weighted_union(inputs: &[(&ScoredSortedSet<T>, i32)]) -> ScoredSortedSet<T>
+    where
+        T: Eq + Hash + Clone,
+    {
+        let mut index: HashMap<T, usize> = HashMap::new();
+        let mut totals: Vec<(T, i64)> = Vec::new(); // In order of first appearance
+        for &(set, weight) in inputs {
+            let inner = lock::read(&set.inner);
+            for (&score, items) in inner.iter() {
+                for item in items {
+                    let slot = *index.entry(item.clone()).or_insert_with(|| {
+                        totals.push((item.clone(), 0));
+                        totals.len() - 1
+                    });
+                    let term = score as i64 * weight as i64;
+                    totals[slot].1 = totals[slot].1.saturating_add(term);
+                }
+            }
+        }
+
+        let mut map: BTreeMap<i32, Vec<T>> = BTreeMap::new();
+        for (item, total) in totals {
+            let score = total.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+            map.entry(score).or_default().push(item);
+        }
+        ScoredSortedSet::new().new_like(map)
+    }
+}
+
+/// Creates a new, empty set, as `with_score_type` does, which for `i32` scores is `new`.
+impl<T, S: Ord + Copy> Default for ScoredSortedSet<T, S> {
+    fn default() -> Self {
+        Self::with_score_type()
+    }
+}
+
+/// Builds a set from `(score, item)` pairs, grouping the items by score in iteration order.
+/// The map is built directly and locked once, rather than locking for each item.
+impl<T, S: Ord + Copy> FromIterator<(S, T)> for ScoredSortedSet<T, S> {
+    fn from_iter<I: IntoIterator<Item = (S, T)>>(iter: I) -> Self {
+        let mut map: BTreeMap<S, Vec<T>> = BTreeMap::new();
+        for (score, item) in iter {
+            map.entry(score).or_default().push(item);
+        }
+        Self::with_score_type().new_like(map)
+    }
+}
+
+/// Formats the set as its scores and their items in ascending order of score, for example
+/// `ScoredSortedSet { scores: {10: ["Alice"], 20: ["Bob", "Charlie"]} }`.
+/// If the set is locked for writing, including by the current thread, `<locked>` is printed
+/// in place of the contents rather than waiting for the lock.
+impl<T: fmt::Debug, S: fmt::Debug> fmt::Debug for ScoredSortedSet<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("ScoredSortedSet");
+        match lock::try_read(&self.inner) {
+            Some(inner) => debug.field("scores", &*inner),
+            None => debug.field("scores", &format_args!("<locked>")),
+        };
+        debug.finish()
+    }
+}
+
+/// Adds `(score, item)` pairs under a single write lock, as `add_many` does.
+impl<T, S: Ord + Copy> Extend<(S, T)> for ScoredSortedSet<T, S> {
+    fn extend<I: IntoIterator<Item = (S, T)>>(&mut self, iter: I) {
+        self.add_many(iter);
+    }
+}
+
+/// Consumes the set and yields every item as a `(score, item)` pair, in ascending order of
+/// score, with items sharing a score in insertion order. Items are moved out rather than cloned.
+impl<T, S: Copy> IntoIterator for ScoredSortedSet<T, S> {
+    type Item = (S, T);
+    type IntoIter = IntoIter<T, S>;
+
+    fn into_iter(self) -> IntoIter<T, S> {
+        IntoIter {
+            scores: lock::into_inner(self.inner).into_iter(),
+            current: None,
+        }
+    }
+}
+
+/// An owning iterator over the items of a `ScoredSortedSet`, created by `into_iter`.
+pub struct IntoIter<T, S = i32> {
+    scores: btree_map::IntoIter<S, Vec<T>>,
+    current: Option<(S, std::vec::IntoIter<T>)>, // Score and remaining items being yielded
+}
+
+impl<T, S: Copy> Iterator for IntoIter<T, S> {
+    type Item = (S, T);
+
+    fn next(&mut self) -> Option<(S, T)> {
+        loop {
+            if let Some((score, items)) = &mut self.current {
+                if let Some(item) = items.next() {
+                    return Some((*score, item));
+                }
+            }
+            let (score, items) = self.scores.next()?;
+            self.current = Some((score, items.into_iter()));
+        }
+    }
+}
+
+/// Settles the bookkeeping for a score being edited in place by `modify_bucket` when dropped,
+/// so it stays in step with the items even if the edit panics.
+struct SettleBucket<'a, T: PartialEq, S: Ord + Copy> {
+    set: &'a ScoredSortedSet<T, S>,
+    inner: &'a mut BTreeMap<S, Vec<T>>,
+    score: S,
+    before: usize,            // Number of items before the edit
+    original: Option<Vec<T>>, // Items before the edit, if restoring or matching needs them
+    restored: bool,           // Whether the original items were put back
+}
+
+impl<T: PartialEq, S: Ord + Copy> SettleBucket<'_, T, S> {
+    /// Puts the original items back if the edit left more than one item in a set with unique
+    /// scores, and returns `true` if it did.
+    fn restore_if_tied(&mut self) -> bool {
+        let Some(items) = self.inner.get_mut(&self.score) else {
+            return false;
+        };
+        if !self.set.unique_scores || items.len() <= 1 {
+            return false;
+        }
+        *items = self.original.take().unwrap();
+        self.restored = true;
+        true
+    }
+}
+
+impl<T: PartialEq, S: Ord + Copy> Drop for SettleBucket<'_, T, S> {
+    fn drop(&mut self) {
+        // Restoring here too covers an edit that panicked
+        if self.restored || self.restore_if_tied() {
+            return; // Nothing changed
+        }
+        let after = self.inner.get(&self.score).map_or(0, Vec::len);
+        self.set.resize_locked(
+            self.inner,
+            self.score,
+            self.before,
+            after,
+            self.original.as_deref(),
+        );
+    }
+}
+
+/// Acquires read locks on two sets in a consistent order, by address, so that calls made
+/// concurrently with the sets swapped cannot deadlock behind a waiting writer.
+/// If both are the same set it is only locked once, and the second guard is `None`.
+#[allow(clippy::type_complexity)]
+fn read_pair<'a, T, S>(
+    a: &'a ScoredSortedSet<T, S>,
+    b: &'a ScoredSortedSet<T, S>,
+) -> (
+    RwLockReadGuard<'a, BTreeMap<S, Vec<T>>>,
+    Option<RwLockReadGuard<'a, BTreeMap<S, Vec<T>>>>,
+) {
+    if std::ptr::eq(a, b) {
+        return (lock::read(&a.inner), None);
+    }
+    if (a as *const ScoredSortedSet<T, S>) < (b as *const ScoredSortedSet<T, S>) {
+        let first = lock::read(&a.inner);
+        (first, Some(lock::read(&b.inner)))
+    } else {
+        let second = lock::read(&b.inner);
+        (lock::read(&a.inner), Some(second))
+    }
+}
+
+/// Returns `true` if no score can lie between `start` and `end`.
+fn is_empty_range<S: Ord>(start: Bound<S>, end: Bound<S>) -> bool {
+    match (start, end) {
+        (Included(min), Included(max)) => min > max,
+        (Included(min), Excluded(max))
+        | (Excluded(min), Included(max))
+        | (Excluded(min), Excluded(max)) => min >= max,
+        _ => false, // Unbounded on either side always admits some score
+    }
+}
+
+/// Returns each distinct item in `map` once, in order of first occurrence.
+fn distinct_items<T: PartialEq, S>(map: &BTreeMap<S, Vec<T>>) -> Vec<&T> {
+    let mut distinct: Vec<&T> = Vec::new();
+    for item in map.values().flatten() {
+        if !distinct.contains(&item) {
+            distinct.push(item);
+        }
+    }
+    distinct
+}
+
+/// Returns the sum of the scores of every item in `map`, each converted by `value`.
+fn sum_of<T, S: Copy>(map: &BTreeMap<S, Vec<T>>, value: fn(S) -> i64) -> i64 {
+    map.iter()
+        .map(|(&score, items)| value(score) * items.len() as i64)
+        .sum()
+}
+
+/// Returns insertion records for every item in `map`, all stamped at version zero.
+fn zero_stamps<T, S: Ord + Copy>(map: &BTreeMap<S, Vec<T>>) -> BTreeMap<S, Vec<u64>> {
+    map.iter()
+        .map(|(&score, items)| (score, vec![0; items.len()]))
+        .collect()
+}
+
+/// Returns the zero-based ascending position of the lowest occurrence of `item` in `map`.
+fn rank_of<T: PartialEq, S: Ord + Copy>(map: &BTreeMap<S, Vec<T>>, item: &T) -> Option<usize> {
+    let (score, pos) = locate(map, item)?;
+    let below: usize = map.range(..score).map(|(_, items)| items.len()).sum();
+    Some(below + pos)
+}
+
+/// Returns a clone of the item at zero-based ascending position `rank` in `map`, with its score.
+fn item_at<T: Clone, S: Copy>(map: &BTreeMap<S, Vec<T>>, mut rank: usize) -> Option<(S, T)> {
+    for (&score, items) in map {
+        match items.get(rank) {
+            Some(item) => return Some((score, item.clone())),
+            None => rank -= items.len(),
+        }
+    }
+    None
+}
+
+/// Finds the lowest score holding `item` and the item's position among the items for that score.
+fn locate<T: PartialEq, S: Copy>(inner: &BTreeMap<S, Vec<T>>, item: &T) -> Option<(S, usize)> {
+    inner.iter().find_map(|(&score, items)| {
+        items
+            .iter()
+            .position(|current_item| current_item == item)
+            .map(|pos| (score, pos))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScoredSortedSet;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_add_and_get() {
+        let set: ScoredSortedSet<String> = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+
+        let items = set.get(10).unwrap();
+        assert_eq!(items, vec!["Alice".to_string()]);
+    }
+
+    #[test]
+    fn test_remove() {
+        let set: ScoredSortedSet<String> = ScoredSortedSet::new();
+        set.add(15, "Bob".to_string());
+        set.add(15, "Charlie".to_string());
+
+        set.remove(15, &"Bob".to_string());
+
+        let items = set.get(15).unwrap();
+        assert_eq!(items, vec!["Charlie".to_string()]);
+    }
+
+    #[test]
+    fn test_remove_nonexistent() {
+        let set: ScoredSortedSet<String> = ScoredSortedSet::new();
+        set.add(20, "Dave".to_string());
+
+        // Attempt to remove an item that doesn't exist
+        set.remove(20, &"Eve".to_string());
+
+        let items = set.get(20).unwrap();
+        assert_eq!(items, vec!["Dave".to_string()]);
+    }
+
+    #[test]
+    fn test_get_nonexistent() {
+        let set: ScoredSortedSet<i32> = ScoredSortedSet::new();
+
+        // Attempt to get items for a score that has no items
+        let items = set.get(25);
+        assert!(items.is_none());
+    }
+
+    #[test]
+    fn test_multiple_scores() {
+        let set: ScoredSortedSet<String> = ScoredSortedSet::new();
+        set.add(30, "Fred".to_string());
+        set.add(40, "George".to_string());
+
+        let items_30 = set.get(30).unwrap();
+        assert_eq!(items_30, vec!["Fred".to_string()]);
+
+        let items_40 = set.get(40).unwrap();
+        assert_eq!(items_40, vec!["George".to_string()]);
+    }
+
+    #[test]
+    fn test_multiple_items_same_score() {
+        let set: ScoredSortedSet<String> = ScoredSortedSet::new();
+        set.add(50, "Hannah".to_string());
+        set.add(50, "Ian".to_string());
+
+        let items = set.get(50).unwrap();
+        assert_eq!(items, vec!["Hannah".to_string(), "Ian".to_string()]);
+    }
+
+    #[test]
+    fn update_score_existing_item() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+
+        set.update_score(10, 20, &"Alice".to_string());
+
+        assert!(
+            set.get(10).is_none(),
+            "Item should be removed from the old score"
+        );
+        assert_eq!(
+            set.get(20).unwrap(),
+            vec!["Alice".to_string()],
+            "Item should exist with the new score"
+        );
+    }
+
+    #[test]
+    fn update_score_nonexistent_item() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+
+        // Attempt to update score of an item that doesn't exist at the specified old score
+        set.update_score(15, 25, &"Bob".to_string());
+
+        assert!(set.get(15).is_none(), "Old score should not have any items");
+        assert!(
+            set.get(25).is_none(),
+            "New score should not have the item because it didn't exist at the old score"
+        );
+        assert_eq!(
+            set.get(10).unwrap(),
+            vec!["Alice".to_string()],
+            "Other items should remain unaffected"
+        );
+    }
+
+    #[test]
+    fn update_score_to_existing_score() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+
+        // Move Alice to the same score as Bob
+        set.update_score(10, 20, &"Alice".to_string());
+
+        assert!(
+            set.get(10).is_none(),
+            "Alice should be removed from the old score"
+        );
+        let items_with_new_score = set.get(20).unwrap();
+        assert!(
+            items_with_new_score.contains(&"Alice".to_string()),
+            "Alice should be added to the new score"
+        );
+        assert!(
+            items_with_new_score.contains(&"Bob".to_string()),
+            "Bob should remain at the new score"
+        );
+    }
+
+    #[test]
+    fn maintain_order_after_update_score() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(10, "Bob".to_string());
+
+        // Update score of Alice, moving her to a new score
+        set.update_score(10, 20, &"Alice".to_string());
+
+        let items_with_old_score = set.get(10).unwrap();
+        assert_eq!(
+            items_with_old_score,
+            vec!["Bob".to_string()],
+            "Only Bob should remain at the old score"
+        );
+
+        let items_with_new_score = set.get(20).unwrap();
+        assert_eq!(
+            items_with_new_score,
+            vec!["Alice".to_string()],
+            "Alice should be present with the new score"
+        );
+    }
+
+    #[test]
+    fn highest_scores_more_than_exists() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+
+        // Request more scores than exist in the set
+        let scores = set.highest_scores(5);
+        assert_eq!(scores.len(), 2, "Should return only the available scores");
+        assert_eq!(scores[0].0, 20, "The highest score should be first");
+        assert_eq!(scores[1].0, 10, "The next highest score should be second");
+    }
+
+    #[test]
+    fn highest_scores_exact_number() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        set.add(30, "Charlie".to_string());
+
+        // Request exactly the number of scores that exist
+        let scores = set.highest_scores(3);
+        assert_eq!(scores.len(), 3, "Should return all available scores");
+        assert_eq!(scores[0].0, 30, "The highest score should be first");
+        assert_eq!(scores[1].0, 20, "The next highest score should be second");
+        assert_eq!(scores[2].0, 10, "The lowest score should be last");
+    }
+
+    #[test]
+    fn highest_scores_ordered_correctly() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(30, "Charlie".to_string());
+        set.add(20, "Bob".to_string());
+
+        // Request scores to verify they are ordered correctly
+        let scores = set.highest_scores(2);
+        assert_eq!(scores.len(), 2, "Should return the top 2 scores");
+        assert_eq!(scores[0].0, 30, "The highest score should be first");
+        assert_eq!(scores[1].0, 20, "The second highest score should be second");
+    }
+
+    #[test]
+    fn highest_scores_none_available() {
+        let set: ScoredSortedSet<String> = ScoredSortedSet::new();
+
+        // Request scores when none are available
+        let scores = set.highest_scores(2);
+        assert!(
+            scores.is_empty(),
+            "Should return an empty vector when no scores are available"
+        );
+    }
+
+    #[test]
+    fn lowest_scores_more_than_exists() {
+        let set = ScoredSortedSet::new();
+        set.add(20, "Bob".to_string());
+        set.add(10, "Alice".to_string());
+
+        // Request more scores than exist in the set
+        let scores = set.lowest_scores(5);
+        assert_eq!(scores.len(), 2, "Should return only the available scores");
+        assert_eq!(scores[0].0, 10, "The lowest score should be first");
+        assert_eq!(scores[1].0, 20, "The next lowest score should be second");
+    }
+
+    #[test]
+    fn lowest_scores_exact_number() {
+        let set = ScoredSortedSet::new();
+        set.add(30, "Charlie".to_string());
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        set.add(10, "Dave".to_string());
+
+        // Request exactly the number of scores that exist
+        let scores = set.lowest_scores(3);
+        assert_eq!(scores.len(), 3, "Should return all available scores");
+        assert_eq!(
+            scores[0],
+            (10, vec!["Alice".to_string(), "Dave".to_string()]),
+            "The lowest score should be first, with all its items"
+        );
+        assert_eq!(scores[1].0, 20, "The next lowest score should be second");
+        assert_eq!(scores[2].0, 30, "The highest score should be last");
+    }
+
+    #[test]
+    fn lowest_scores_none_available() {
+        let set: ScoredSortedSet<String> = ScoredSortedSet::new();
+
+        // Request scores when none are available
+        let scores = set.lowest_scores(2);
+        assert!(
+            scores.is_empty(),
+            "Should return an empty vector when no scores are available"
+        );
+    }
+
+    #[test]
+    fn lowest_and_highest_score_empty_set() {
+        let set: ScoredSortedSet<String> = ScoredSortedSet::new();
+
+        assert!(
+            set.lowest_score().is_none(),
+            "Should be None for an empty set"
+        );
+        assert!(
+            set.highest_score().is_none(),
+            "Should be None for an empty set"
+        );
+    }
+
+    #[test]
+    fn lowest_and_highest_score_single_item() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+
+        let lowest = set.lowest_score().unwrap();
+        assert_eq!(lowest.0, 10, "Lowest score should be 10");
+        assert_eq!(
+            lowest.1,
+            vec!["Alice".to_string()],
+            "Lowest score item should be Alice"
+        );
+
+        let highest = set.highest_score().unwrap();
+        assert_eq!(highest.0, 10, "Highest score should also be 10");
+        assert_eq!(
+            highest.1,
+            vec!["Alice".to_string()],
+            "Highest score item should also be Alice"
+        );
+    }
+
+    #[test]
+    fn lowest_and_highest_score_multiple_items() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        set.add(30, "Charlie".to_string());
+
+        let lowest = set.lowest_score().unwrap();
+        assert_eq!(lowest.0, 10, "Lowest score should be 10");
+        assert_eq!(
+            lowest.1,
+            vec!["Alice".to_string()],
+            "Lowest score item should be Alice"
+        );
+
+        let highest = set.highest_score().unwrap();
+        assert_eq!(highest.0, 30, "Highest score should be 30");
+        assert_eq!(
+            highest.1,
+            vec!["Charlie".to_string()],
+            "Highest score item should be Charlie"
+        );
+    }
+
+    #[test]
+    fn updating_scores_affects_lowest_and_highest_correctly() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+
+        set.update_score(10, 5, &"Alice".to_string()); // Update Alice to a lower score
+
+        let lowest = set.lowest_score().unwrap();
+        assert_eq!(lowest.0, 5, "After update, lowest score should be 5");
+        assert_eq!(
+            lowest.1,
+            vec!["Alice".to_string()],
+            "Lowest score item should now be Alice"
+        );
+
+        let highest = set.highest_score().unwrap();
+        assert_eq!(highest.0, 20, "Highest score remains 20");
+        assert_eq!(
+            highest.1,
+            vec!["Bob".to_string()],
+            "Highest score item remains Bob"
+        );
+    }
+
+    #[test]
+    fn all_scores_empty_set() {
+        let set = ScoredSortedSet::<String>::new();
+        let scores = set.all_scores();
+        assert!(scores.is_empty(), "Expected no scores for an empty set");
+    }
+
+    #[test]
+    fn all_scores_non_empty_set() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        set.add(30, "Charlie".to_string());
+
+        let scores = set.all_scores();
+        assert_eq!(scores.len(), 3, "Expected three scores in the set");
+        assert_eq!(
+            scores,
+            vec![10, 20, 30],
+            "Scores should be in ascending order"
+        );
+    }
+
+    // This tests the unique nature of scores implicitly
+    #[test]
+    fn all_scores_with_duplicate_scores() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(10, "Duplicate Alice".to_string()); // Duplicate score
+        set.add(20, "Bob".to_string());
+
+        let scores = set.all_scores();
+        assert_eq!(scores.len(), 2, "Expected scores to be unique");
+        assert_eq!(
+            scores,
+            vec![10, 20],
+            "Scores should be in ascending order and unique"
+        );
+    }
+
+    #[test]
+    fn strict_ranking_breaks_ties() {
+        let set = ScoredSortedSet::new();
+        set.add(20, "Bob".to_string());
+        set.add(10, "Zed".to_string());
+        set.add(10, "Alice".to_string());
+
+        let ranking = set.strict_ranking(|a, b| a.cmp(b));
+        assert_eq!(
+            ranking,
+            vec![
+                (1, 10, "Alice".to_string()),
+                (2, 10, "Zed".to_string()),
+                (3, 20, "Bob".to_string()),
+            ],
+            "Tied items should be ordered by the tie-break and receive unique ranks"
+        );
+    }
+
+    #[test]
+    fn strict_ranking_empty_set() {
+        let set: ScoredSortedSet<String> = ScoredSortedSet::new();
+        assert!(
+            set.strict_ranking(|a, b| a.cmp(b)).is_empty(),
+            "An empty set should produce an empty ranking"
+        );
+    }
+
+    #[test]
+    fn bottom_items_cuts_through_tie_group() {
+        let set = ScoredSortedSet::new();
+        set.add(30, "Charlie".to_string());
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        set.add(20, "Dave".to_string());
+
+        let bottom = set.bottom_items(2);
+        assert_eq!(
+            bottom,
+            vec![(10, "Alice".to_string()), (20, "Bob".to_string())],
+            "Only enough of the tied items should be included to reach k"
+        );
+    }
+
+    #[test]
+    fn bottom_items_more_than_exists() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+
+        assert_eq!(set.bottom_items(5).len(), 2, "Should return every item");
+        assert!(
+            set.bottom_items(0).is_empty(),
+            "k = 0 should return nothing"
+        );
+    }
+
+    #[test]
+    fn score_bounds_reject_out_of_range_add() {
+        let set = ScoredSortedSet::with_score_bounds(0, 100);
+        assert!(set.add(0, "Alice".to_string()), "Lower bound is inclusive");
+        assert!(set.add(100, "Bob".to_string()), "Upper bound is inclusive");
+        assert!(
+            !set.add(101, "Charlie".to_string()),
+            "Above max is rejected"
+        );
+        assert!(!set.add(-1, "Dave".to_string()), "Below min is rejected");
+
+        assert_eq!(
+            set.all_scores(),
+            vec![0, 100],
+            "Rejected adds make no change"
+        );
+    }
+
+    #[test]
+    fn score_bounds_reject_out_of_range_update() {
+        let set = ScoredSortedSet::with_score_bounds(0, 100);
+        set.add(50, "Alice".to_string());
+
+        assert!(
+            !set.update_score(50, 150, &"Alice".to_string()),
+            "Moving outside the bounds should be rejected"
+        );
+        assert_eq!(
+            set.get(50).unwrap(),
+            vec!["Alice".to_string()],
+            "The item should stay at its old score"
+        );
+        assert!(set.update_score(50, 60, &"Alice".to_string()));
+        assert_eq!(set.get(60).unwrap(), vec!["Alice".to_string()]);
+    }
+
+    #[test]
+    fn tier_returns_count_and_items() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(10, "Bob".to_string());
+
+        let (count, items) = set.tier(10).unwrap();
+        assert_eq!(count, 2, "Count should match the number of items");
+        assert_eq!(items, vec!["Alice".to_string(), "Bob".to_string()]);
+        assert!(set.tier(20).is_none(), "Absent score should return None");
+    }
+
+    #[test]
+    fn scores_in_range_inclusive_bounds() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        set.add(20, "Charlie".to_string());
+        set.add(30, "Dave".to_string());
+
+        assert_eq!(
+            set.scores_in_range(10, 20),
+            vec![10, 20],
+            "Bounds are inclusive"
+        );
+        assert_eq!(set.scores_in_range(11, 29), vec![20]);
+        assert!(set.scores_in_range(31, 40).is_empty());
+    }
+
+    #[test]
+    fn scores_in_range_inverted_bounds() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+
+        assert!(
+            set.scores_in_range(20, 0).is_empty(),
+            "Inverted bounds should return an empty vector"
+        );
+    }
+
+    #[test]
+    fn rank_for_score_counts_items_at_or_below() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        set.add(20, "Charlie".to_string());
+        set.add(30, "Dave".to_string());
+
+        assert_eq!(set.rank_for_score(5), 0, "Nothing is at or below 5");
+        assert_eq!(
+            set.rank_for_score(20),
+            3,
+            "Ties at the given score are counted"
+        );
+        assert_eq!(set.rank_for_score(25), 3);
+        assert_eq!(set.rank_for_score(i32::MAX), 4);
+        assert_eq!(
+            set.all_scores(),
+            vec![10, 20, 30],
+            "The set is not modified"
+        );
+    }
+
+    #[test]
+    fn raw_write_allows_custom_sequences() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+
+        {
+            let mut inner = set.raw_write();
+            let items = inner.remove(&10).unwrap();
+            inner.insert(40, items);
+        }
+
+        assert!(set.get(10).is_none());
+        assert_eq!(set.get(40).unwrap(), vec!["Alice".to_string()]);
+    }
+
+    #[test]
+    fn rank_map_assigns_ascending_ranks() {
+        let set = ScoredSortedSet::new();
+        set.add(20, "Charlie".to_string());
+        set.add(10, "Alice".to_string());
+        set.add(10, "Bob".to_string());
+
+        let ranks = set.rank_map();
+        assert_eq!(ranks.len(), 3);
+        assert_eq!(ranks["Alice"], 0, "Lowest score gets rank 0");
+        assert_eq!(ranks["Bob"], 1, "Ties are ranked in insertion order");
+        assert_eq!(ranks["Charlie"], 2);
+    }
+
+    #[test]
+    fn rank_map_duplicate_items_take_last_rank() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        set.add(30, "Alice".to_string());
+
+        let ranks = set.rank_map();
+        assert_eq!(ranks.len(), 2);
+        assert_eq!(ranks["Alice"], 2, "The last occurrence should win");
+    }
+
+    #[test]
+    fn partition_by_score_leaves_original_untouched() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        set.add(30, "Charlie".to_string());
+
+        let (below, at_or_above) = set.partition_by_score(20);
+        assert_eq!(
+            below.all_scores(),
+            vec![10],
+            "Below should exclude the split score"
+        );
+        assert_eq!(
+            at_or_above.all_scores(),
+            vec![20, 30],
+            "At-or-above should include the split score"
+        );
+        assert_eq!(
+            set.all_scores(),
+            vec![10, 20, 30],
+            "Original should be unchanged"
+        );
+
+        below.add(5, "Dave".to_string());
+        assert!(
+            set.get(5).is_none(),
+            "Partitions should be independent copies"
+        );
+    }
+
+    #[test]
+    fn aggregates_without_caching() {
+        let set = ScoredSortedSet::new();
+        assert_eq!(set.len(), 0);
+        assert_eq!(set.sum_of_scores(), 0);
+        assert!(set.mean_score().is_none(), "Mean of an empty set is None");
+
+        set.add(10, "Alice".to_string());
+        set.add(10, "Bob".to_string());
+        set.add(40, "Charlie".to_string());
+
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.sum_of_scores(), 60, "Shared scores count once per item");
+        assert_eq!(set.mean_score(), Some(20.0));
+    }
+
+    #[test]
+    fn cached_aggregates_track_mutations() {
+        let set = ScoredSortedSet::with_cached_aggregates();
+        set.add(10, "Alice".to_string());
+        set.add(10, "Bob".to_string());
+        set.add(40, "Charlie".to_string());
+        set.update_score(10, 20, &"Alice".to_string());
+        set.remove(40, &"Charlie".to_string());
+        set.remove(40, &"Nobody".to_string());
+
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.sum_of_scores(), 30);
+        assert_eq!(set.mean_score(), Some(15.0));
+
+        set.remove(10, &"Bob".to_string());
+        set.remove(20, &"Alice".to_string());
+        assert_eq!(set.len(), 0);
+        assert_eq!(set.sum_of_scores(), 0);
+        assert!(set.mean_score().is_none());
+    }
+
+    #[test]
+    fn cached_aggregates_avoid_overflow() {
+        let set = ScoredSortedSet::with_cached_aggregates();
+        set.add(i32::MAX, "Alice".to_string());
+        set.add(i32::MAX, "Bob".to_string());
+
+        assert_eq!(set.sum_of_scores(), 2 * i32::MAX as i64);
+    }
+
+    #[test]
+    fn score_statistics_weight_shared_scores() {
+        let plain = ScoredSortedSet::new();
+        let cached = ScoredSortedSet::with_cached_aggregates();
+        for set in [&plain, &cached] {
+            for i in 0..5 {
+                set.add(100, i);
+            }
+            set.add(-50, 5);
+
+            assert_eq!(set.sum_of_scores(), 450, "Score 100 counts five times");
+            assert_eq!(set.mean_score(), Some(75.0));
+            assert_eq!(set.lowest_score().unwrap().0, -50);
+            assert_eq!(set.highest_score().unwrap().0, 100);
+        }
+    }
+
+    #[test]
+    fn cached_aggregates_carry_over_to_partitions() {
+        let set = ScoredSortedSet::with_cached_aggregates();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        set.add(30, "Charlie".to_string());
+
+        let (below, at_or_above) = set.partition_by_score(20);
+        assert_eq!(below.len(), 1);
+        assert_eq!(below.sum_of_scores(), 10);
+        assert_eq!(at_or_above.len(), 2);
+        assert_eq!(at_or_above.sum_of_scores(), 50);
+    }
+
+    #[test]
+    fn best_per_key_keeps_highest_score() {
+        let set = ScoredSortedSet::new();
+        set.add(10, ("red", "Alice"));
+        set.add(30, ("blue", "Bob"));
+        set.add(20, ("red", "Charlie"));
+        set.add(20, ("red", "Dave"));
+        set.add(5, ("blue", "Eve"));
+
+        let best = set.best_per_key(|&(team, _)| team);
+        assert_eq!(best.len(), 2, "One entry per team");
+        assert_eq!(
+            best["red"],
+            (20, ("red", "Charlie")),
+            "Ties at the best score keep the first inserted item"
+        );
+        assert_eq!(best["blue"], (30, ("blue", "Bob")));
+    }
+
+    #[test]
+    fn are_tied_compares_scores() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(10, "Bob".to_string());
+        set.add(20, "Charlie".to_string());
+
+        let (alice, bob, charlie) = (
+            "Alice".to_string(),
+            "Bob".to_string(),
+            "Charlie".to_string(),
+        );
+        assert_eq!(set.are_tied(&alice, &bob), Some(true));
+        assert_eq!(set.are_tied(&alice, &charlie), Some(false));
+        assert_eq!(
+            set.are_tied(&alice, &alice),
+            Some(true),
+            "An item is tied with itself"
+        );
+        assert_eq!(
+            set.are_tied(&alice, &"Nobody".to_string()),
+            None,
+            "A missing item cannot be compared"
+        );
+    }
+
+    #[test]
+    fn get_scores_flattens_listed_scores() {
+        let set = ScoredSortedSet::new();
+        set.add(100, "Alice".to_string());
+        set.add(500, "Bob".to_string());
+        set.add(500, "Charlie".to_string());
+        set.add(750, "Dave".to_string());
+
+        let items = set.get_scores(&[1000, 500, 100, 500]);
+        assert_eq!(
+            items,
+            vec![
+                (100, "Alice".to_string()),
+                (500, "Bob".to_string()),
+                (500, "Charlie".to_string()),
+            ],
+            "Items should be ascending, with absent and repeated scores skipped"
+        );
+        assert!(set.get_scores(&[]).is_empty());
+    }
+
+    #[test]
+    fn into_grouped_moves_buckets_out() {
+        let set = ScoredSortedSet::new();
+        set.add(20, "Charlie".to_string());
+        set.add(10, "Alice".to_string());
+        set.add(10, "Bob".to_string());
+
+        assert_eq!(
+            set.into_grouped(),
+            vec![
+                (10, vec!["Alice".to_string(), "Bob".to_string()]),
+                (20, vec!["Charlie".to_string()]),
+            ],
+            "Buckets should be ascending with items in insertion order"
+        );
+    }
+
+    #[test]
+    fn count_scores_with_at_least_counts_buckets() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        set.add(20, "Charlie".to_string());
+        set.add(30, "Dave".to_string());
+        set.add(30, "Eve".to_string());
+        set.add(30, "Fred".to_string());
+
+        assert_eq!(set.count_scores_with_at_least(1), 3);
+        assert_eq!(set.count_scores_with_at_least(2), 2);
+        assert_eq!(set.count_scores_with_at_least(3), 1);
+        assert_eq!(set.count_scores_with_at_least(4), 0);
+    }
+
+    #[test]
+    fn item_ahead_resolves_ties_first() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(10, "Bob".to_string());
+        set.add(20, "Charlie".to_string());
+
+        assert_eq!(
+            set.item_ahead(&"Alice".to_string()),
+            Some((10, "Bob".to_string())),
+            "The next tied item should come first"
+        );
+        assert_eq!(
+            set.item_ahead(&"Bob".to_string()),
+            Some((20, "Charlie".to_string()))
+        );
+        assert!(
+            set.item_ahead(&"Charlie".to_string()).is_none(),
+            "Leader has no one ahead"
+        );
+        assert!(set.item_ahead(&"Nobody".to_string()).is_none());
+    }
+
+    #[test]
+    fn item_behind_mirrors_item_ahead() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        set.add(20, "Charlie".to_string());
+
+        assert_eq!(
+            set.item_behind(&"Charlie".to_string()),
+            Some((20, "Bob".to_string()))
+        );
+        assert_eq!(
+            set.item_behind(&"Bob".to_string()),
+            Some((10, "Alice".to_string()))
+        );
+        assert!(set.item_behind(&"Alice".to_string()).is_none());
+    }
+
+    #[test]
+    fn set_scores_moves_and_inserts() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+
+        let applied = set.set_scores(&[("Alice".to_string(), 30), ("Charlie".to_string(), 20)]);
+        assert_eq!(applied, 2);
+        assert!(set.get(10).is_none(), "Alice should have moved");
+        assert_eq!(set.get(30).unwrap(), vec!["Alice".to_string()]);
+        assert_eq!(
+            set.get(20).unwrap(),
+            vec!["Bob".to_string(), "Charlie".to_string()],
+            "Absent items should be inserted"
+        );
+    }
+
+    #[test]
+    fn set_scores_skips_out_of_bounds() {
+        let set = ScoredSortedSet::with_score_bounds(0, 100);
+        set.add(10, "Alice".to_string());
+
+        let applied = set.set_scores(&[("Alice".to_string(), 200), ("Bob".to_string(), 50)]);
+        assert_eq!(applied, 1, "Only the in-bounds update should apply");
+        assert_eq!(set.get(10).unwrap(), vec!["Alice".to_string()]);
+        assert_eq!(set.get(50).unwrap(), vec!["Bob".to_string()]);
+    }
+
+    #[test]
+    fn anonymized_distribution_bands_scores() {
+        let set = ScoredSortedSet::new();
+        set.add(-5, "Alice".to_string());
+        set.add(3, "Bob".to_string());
+        set.add(7, "Charlie".to_string());
+        set.add(7, "Dave".to_string());
+        set.add(25, "Eve".to_string());
+
+        assert_eq!(
+            set.anonymized_distribution(10),
+            vec![(-10, 1), (0, 3), (20, 1)],
+            "Counts should be grouped into populated bands"
+        );
+    }
+
+    #[test]
+    fn anonymized_distribution_extreme_scores() {
+        let set = ScoredSortedSet::new();
+        set.add(i32::MIN, "Alice".to_string());
+        set.add(i32::MAX, "Bob".to_string());
+
+        let bands = set.anonymized_distribution(3);
+        assert_eq!(
+            bands[0],
+            (i32::MIN, 1),
+            "Lowest band is clamped to i32::MIN"
+        );
+        assert_eq!(bands[1], (i32::MAX - 1, 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "bucket_width must be positive")]
+    fn anonymized_distribution_rejects_zero_width() {
+        let set: ScoredSortedSet<String> = ScoredSortedSet::new();
+        set.anonymized_distribution(0);
+    }
+
+    #[test]
+    fn version_bumps_on_mutation() {
+        let set = ScoredSortedSet::new();
+        assert_eq!(set.version(), 0);
+
+        set.add(10, "Alice".to_string());
+        let after_add = set.version();
+        assert!(after_add > 0, "Adding should bump the version");
+
+        set.get(10);
+        set.remove(10, &"Nobody".to_string());
+        assert_eq!(
+            set.version(),
+            after_add,
+            "Reads and failed removals leave it alone"
+        );
+
+        set.update_score(10, 10, &"Alice".to_string());
+        let after_update = set.version();
+        assert!(after_update > after_add, "Same-score updates still mutate");
+
+        set.remove(10, &"Alice".to_string());
+        assert!(
+            set.version() > after_update,
+            "Removing should bump the version"
+        );
+    }
+
+    #[test]
+    fn tier_rank_ranges_accumulate_counts() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        set.add(20, "Charlie".to_string());
+        set.add(30, "Dave".to_string());
+
+        assert_eq!(
+            set.tier_rank_ranges(),
+            vec![(10, 0, 1), (20, 1, 3), (30, 3, 4)],
+            "Each tier should cover the ranks of its items"
+        );
+        assert!(ScoredSortedSet::<String>::new()
+            .tier_rank_ranges()
+            .is_empty());
+    }
+
+    #[test]
+    fn remove_if_score_checks_current_score() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.update_score(10, 20, &"Alice".to_string());
+
+        assert!(
+            !set.remove_if_score(&"Alice".to_string(), 10),
+            "An item that has moved should not be removed"
+        );
+        assert_eq!(set.get(20).unwrap(), vec!["Alice".to_string()]);
+
+        assert!(set.remove_if_score(&"Alice".to_string(), 20));
+        assert!(set.get(20).is_none(), "The emptied score should be removed");
+        assert!(set.all_scores().is_empty());
+    }
+
+    #[test]
+    fn dense_ranked_items_share_ranks() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(10, "Bob".to_string());
+        set.add(20, "Charlie".to_string());
+
+        assert_eq!(
+            set.dense_ranked_items(),
+            vec![
+                (1, 10, "Alice".to_string()),
+                (1, 10, "Bob".to_string()),
+                (2, 20, "Charlie".to_string()),
+            ],
+            "Ties share a rank and the next rank is not skipped"
+        );
+    }
+
+    #[test]
+    fn dense_ranked_items_desc_ranks_from_top() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        set.add(20, "Charlie".to_string());
+
+        assert_eq!(
+            set.dense_ranked_items_desc(),
+            vec![
+                (1, 20, "Charlie".to_string()),
+                (1, 20, "Bob".to_string()),
+                (2, 10, "Alice".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn items_since_reports_new_items() {
+        let set = ScoredSortedSet::with_insertion_tracking();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        let seen = set.version();
+
+        set.add(5, "Charlie".to_string());
+        set.add(20, "Dave".to_string());
+        assert_eq!(
+            set.items_since(seen),
+            vec![(5, "Charlie".to_string()), (20, "Dave".to_string())],
+            "Only items added after the version should be returned"
+        );
+        assert_eq!(
+            set.items_since(0).len(),
+            4,
+            "Version zero returns everything"
+        );
+        assert!(set.items_since(set.version()).is_empty());
+    }
+
+    #[test]
+    fn items_since_moves_and_removals() {
+        let set = ScoredSortedSet::with_insertion_tracking();
+        set.add(10, "Alice".to_string());
+        set.add(10, "Bob".to_string());
+        set.add(10, "Charlie".to_string());
+        let seen = set.version();
+
+        set.remove(10, &"Bob".to_string());
+        set.update_score(10, 30, &"Alice".to_string());
+        set.add(40, "Bob".to_string());
+
+        assert_eq!(
+            set.items_since(seen),
+            vec![(30, "Alice".to_string()), (40, "Bob".to_string())],
+            "Moved and re-added items count as new, and the unchanged item does not"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "with_insertion_tracking")]
+    fn items_since_requires_tracking() {
+        let set: ScoredSortedSet<String> = ScoredSortedSet::new();
+        set.items_since(0);
+    }
+
+    #[test]
+    fn bucketize_into_tiers_splits_evenly() {
+        let set = ScoredSortedSet::new();
+        for (score, name) in [(10, "A"), (20, "B"), (20, "C"), (30, "D"), (40, "E")] {
+            set.add(score, name);
+        }
+
+        let tiers = set.bucketize_into_tiers(2);
+        assert_eq!(tiers.len(), 2);
+        assert_eq!(tiers[0], vec![(10, "A"), (20, "B")]);
+        assert_eq!(
+            tiers[1],
+            vec![(20, "C"), (30, "D"), (40, "E")],
+            "Tiers should be contiguous and differ in size by at most one"
+        );
+    }
+
+    #[test]
+    fn bucketize_into_tiers_edge_cases() {
+        let set = ScoredSortedSet::new();
+        assert!(
+            set.bucketize_into_tiers(0).is_empty(),
+            "Zero tiers yields no groups"
+        );
+
+        let tiers: Vec<Vec<(i32, &str)>> = set.bucketize_into_tiers(3);
+        assert_eq!(tiers.len(), 3, "An empty set still yields every tier");
+        assert!(tiers.iter().all(Vec::is_empty));
+
+        set.add(10, "A");
+        let tiers = set.bucketize_into_tiers(3);
+        assert_eq!(tiers.iter().map(Vec::len).sum::<usize>(), 1);
+    }
+
+    #[test]
+    fn len_matches_recount_after_random_operations() {
+        let set = ScoredSortedSet::new();
+        let mut seed: u32 = 12345;
+        let mut next = move |bound: u32| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345); // Simple LCG
+            (seed >> 16) % bound
+        };
+
+        for _ in 0..2000 {
+            let score = next(20) as i32;
+            let item = next(30);
+            match next(5) {
+                0 | 1 => {
+                    set.add(score, item);
+                }
+                2 => {
+                    set.remove(score, &item);
+                }
+                3 => {
+                    set.update_score(score, next(20) as i32, &item);
+                }
+                _ => {
+                    set.set_scores(&[(item, score), (next(30), next(20) as i32)]);
+                }
+            }
+            if next(10) == 0 {
+                set.remove_if_score(&item, score);
+            }
+
+            let recount: usize = set.raw_read().values().map(Vec::len).sum();
+            assert_eq!(
+                set.len(),
+                recount,
+                "Atomic length should match a full recount"
+            );
+        }
+    }
+
+    #[test]
+    fn max_item_per_score_uses_item_ordering() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Bob".to_string());
+        set.add(10, "Dave".to_string());
+        set.add(10, "Alice".to_string());
+        set.add(20, "Charlie".to_string());
+
+        assert_eq!(
+            set.max_item_per_score(),
+            vec![(10, "Dave".to_string()), (20, "Charlie".to_string())],
+            "Each score should report its largest item, not its first"
+        );
+    }
+
+    #[test]
+    fn score_of_returns_lowest_score() {
+        let set = ScoredSortedSet::new();
+        set.add(30, "Alice".to_string());
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+
+        assert_eq!(set.score_of(&"Alice".to_string()), Some(10));
+        assert_eq!(set.score_of(&"Bob".to_string()), Some(20));
+        assert_eq!(set.score_of(&"Nobody".to_string()), None);
+    }
+
+    #[test]
+    fn add_f64_rounds_to_thousandths() {
+        let set = ScoredSortedSet::new();
+        assert!(set.add_f64(12.345, "Alice".to_string()));
+        assert!(set.add_f64(12.3456, "Bob".to_string()));
+        assert!(set.add_f64(-0.0005, "Charlie".to_string()));
+
+        assert_eq!(set.get(12345).unwrap(), vec!["Alice".to_string()]);
+        assert_eq!(set.score_of_f64(&"Alice".to_string()), Some(12.345));
+        assert_eq!(
+            set.score_of_f64(&"Bob".to_string()),
+            Some(12.346),
+            "Extra precision should be rounded"
+        );
+        assert_eq!(
+            set.score_of(&"Charlie".to_string()),
+            Some(-1),
+            "Halfway rounds away from zero"
+        );
+    }
+
+    #[test]
+    fn add_f64_rejects_unrepresentable_scores() {
+        let set = ScoredSortedSet::new();
+        assert!(!set.add_f64(f64::NAN, "Alice".to_string()));
+        assert!(!set.add_f64(f64::INFINITY, "Bob".to_string()));
+        assert!(!set.add_f64(2_147_483.648, "Charlie".to_string()));
+        assert!(set.add_f64(2_147_483.647, "Dave".to_string()));
+        assert_eq!(set.all_scores(), vec![i32::MAX]);
+    }
+
+    #[test]
+    fn median_tier_returns_whole_tie_group() {
+        let set = ScoredSortedSet::new();
+        assert!(set.median_tier().is_none(), "Empty set has no median");
+
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        set.add(20, "Charlie".to_string());
+        set.add(30, "Dave".to_string());
+        set.add(40, "Eve".to_string());
+
+        assert_eq!(
+            set.median_tier(),
+            Some((20, vec!["Bob".to_string(), "Charlie".to_string()]))
+        );
+    }
+
+    #[test]
+    fn median_tier_uses_lower_median() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        set.add(30, "Charlie".to_string());
+        set.add(40, "Dave".to_string());
+
+        assert_eq!(
+            set.median_tier(),
+            Some((20, vec!["Bob".to_string()])),
+            "An even count should use the lower median"
+        );
+    }
+
+    #[test]
+    fn range_is_empty_checks_window() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(30, "Bob".to_string());
+
+        assert!(!set.range_is_empty(10, 10), "Bounds are inclusive");
+        assert!(!set.range_is_empty(0, 100));
+        assert!(set.range_is_empty(11, 29), "Gap between scores is empty");
+        assert!(set.range_is_empty(30, 10), "Inverted bounds are empty");
+    }
+
+    #[test]
+    fn to_columns_aligns_scores_and_items() {
+        let set = ScoredSortedSet::new();
+        set.add(20, "Charlie".to_string());
+        set.add(10, "Alice".to_string());
+        set.add(10, "Bob".to_string());
+
+        let (scores, items) = set.to_columns();
+        assert_eq!(scores, vec![10, 10, 20]);
+        assert_eq!(
+            items,
+            vec![
+                "Alice".to_string(),
+                "Bob".to_string(),
+                "Charlie".to_string()
+            ],
+            "Items should line up with their scores"
+        );
+    }
+
+    #[test]
+    fn retain_scores_drops_whole_tiers() {
+        let set = ScoredSortedSet::with_cached_aggregates();
+        set.add(-5, "Alice".to_string());
+        set.add(10, "Bob".to_string());
+        set.add(20, "Charlie".to_string());
+        set.add(20, "Dave".to_string());
+
+        set.retain_scores(|_, count| count > 1);
+        assert_eq!(
+            set.all_scores(),
+            vec![20],
+            "Single-item tiers should be dropped"
+        );
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.sum_of_scores(), 40);
+
+        set.retain_scores(|score, _| score < 0);
+        assert!(set.all_scores().is_empty());
+        assert_eq!(set.len(), 0);
+    }
+
+    #[test]
+    fn points_to_overtake_computes_gap() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(25, "Bob".to_string());
+        set.add(25, "Charlie".to_string());
+
+        let (alice, bob, charlie) = (
+            "Alice".to_string(),
+            "Bob".to_string(),
+            "Charlie".to_string(),
+        );
+        assert_eq!(set.points_to_overtake(&alice, &bob), Some(16));
+        assert_eq!(
+            set.points_to_overtake(&bob, &charlie),
+            Some(1),
+            "A tie needs one point"
+        );
+        assert_eq!(
+            set.points_to_overtake(&bob, &alice),
+            Some(0),
+            "Already ahead"
+        );
+        assert_eq!(set.points_to_overtake(&alice, &"Nobody".to_string()), None);
+    }
+
+    #[test]
+    fn points_to_overtake_saturates() {
+        let set = ScoredSortedSet::new();
+        set.add(i32::MIN, "Alice".to_string());
+        set.add(i32::MAX, "Bob".to_string());
+
+        assert_eq!(
+            set.points_to_overtake(&"Alice".to_string(), &"Bob".to_string()),
+            Some(i32::MAX)
+        );
+    }
+
+    #[test]
+    fn get_or_empty_defaults_to_empty() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+
+        assert_eq!(set.get_or_empty(10), vec!["Alice".to_string()]);
+        assert!(
+            set.get_or_empty(20).is_empty(),
+            "Absent score gives an empty vector"
+        );
+    }
+
+    #[test]
+    fn membership_diff_splits_by_value() {
+        let local = ScoredSortedSet::new();
+        local.add(10, "Alice".to_string());
+        local.add(20, "Bob".to_string());
+        local.add(30, "Alice".to_string());
+
+        let remote = ScoredSortedSet::new();
+        remote.add(50, "Bob".to_string());
+        remote.add(5, "Charlie".to_string());
+
+        let (only_local, only_remote, both) = local.membership_diff(&remote);
+        assert_eq!(
+            only_local,
+            vec!["Alice".to_string()],
+            "Duplicates appear once"
+        );
+        assert_eq!(only_remote, vec!["Charlie".to_string()]);
+        assert_eq!(both, vec!["Bob".to_string()], "Scores are ignored");
+    }
+
+    #[test]
+    fn membership_diff_with_itself() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+
+        let (only_self, only_other, both) = set.membership_diff(&set);
+        assert!(only_self.is_empty());
+        assert!(only_other.is_empty());
+        assert_eq!(both, vec!["Alice".to_string(), "Bob".to_string()]);
+    }
+
+    #[test]
+    fn swap_buckets_exchanges_items() {
+        let set = ScoredSortedSet::with_cached_aggregates();
+        set.add(10, "Alice".to_string());
+        set.add(10, "Bob".to_string());
+        set.add(20, "Charlie".to_string());
+
+        assert!(set.swap_buckets(10, 20));
+        assert_eq!(set.get(10).unwrap(), vec!["Charlie".to_string()]);
+        assert_eq!(
+            set.get(20).unwrap(),
+            vec!["Alice".to_string(), "Bob".to_string()]
+        );
+        assert_eq!(set.len(), 3);
+        assert_eq!(
+            set.sum_of_scores(),
+            50,
+            "Cached sum should follow the moved items"
+        );
+    }
+
+    #[test]
+    fn swap_buckets_requires_both_scores() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+
+        assert!(!set.swap_buckets(10, 30), "Missing score should fail");
+        assert_eq!(set.get(10).unwrap(), vec!["Alice".to_string()]);
+        assert!(
+            set.swap_buckets(10, 10),
+            "Same existing score is a successful no-op"
+        );
+        assert!(!set.swap_buckets(30, 30));
+    }
+
+    #[test]
+    fn tier_previews_one_item_per_score() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        set.add(20, "Charlie".to_string());
+        set.add(30, "Dave".to_string());
+
+        assert_eq!(
+            set.tier_previews(2),
+            vec![(30, "Dave".to_string()), (20, "Bob".to_string())],
+            "Each tier should be represented by its first item"
+        );
+        assert_eq!(set.tier_previews(10).len(), 3);
+    }
+
+    #[test]
+    fn prepare_scores_reserves_capacity() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.prepare_scores(&[(10, 64), (20, 32)]);
+
+        assert_eq!(
+            set.all_scores(),
+            vec![10],
+            "No empty scores should be created"
+        );
+        assert!(set.raw_read()[&10].capacity() >= 64);
+
+        set.add(20, "Bob".to_string());
+        let inner = set.raw_read();
+        assert!(
+            inner[&20].capacity() >= 32,
+            "The reservation should apply on the first add"
+        );
+        assert_eq!(inner[&20], vec!["Bob".to_string()]);
+    }
+
+    #[test]
+    fn for_each_in_order_follows_given_order() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        set.add(20, "Charlie".to_string());
+        set.add(30, "Dave".to_string());
+
+        let mut visited = Vec::new();
+        set.for_each_in_order(&[30, 99, 10, 20], |score, items| {
+            visited.push((score, items.len()));
+        });
+        assert_eq!(
+            visited,
+            vec![(30, 1), (10, 1), (20, 2)],
+            "Scores should be visited in the given order, skipping absent ones"
+        );
+    }
+
+    #[test]
+    fn largest_score_gap_finds_widest() {
+        let set = ScoredSortedSet::new();
+        assert!(set.largest_score_gap().is_none());
+        set.add(10, "Alice".to_string());
+        assert!(set.largest_score_gap().is_none(), "One score has no gaps");
+
+        set.add(15, "Bob".to_string());
+        set.add(40, "Charlie".to_string());
+        set.add(50, "Dave".to_string());
+        set.add(75, "Eve".to_string());
+        assert_eq!(
+            set.largest_score_gap(),
+            Some((15, 40, 25)),
+            "Equally wide gaps should resolve to the lowest"
+        );
+    }
+
+    #[test]
+    fn largest_score_gap_saturates() {
+        let set = ScoredSortedSet::new();
+        set.add(i32::MIN, "Alice".to_string());
+        set.add(i32::MAX, "Bob".to_string());
+
+        assert_eq!(
+            set.largest_score_gap(),
+            Some((i32::MIN, i32::MAX, i32::MAX))
+        );
+    }
+
+    #[test]
+    fn adjust_scores_where_moves_matching_items() {
+        let set = ScoredSortedSet::with_cached_aggregates();
+        set.add(10, ("eu", "Alice"));
+        set.add(10, ("us", "Bob"));
+        set.add(60, ("us", "Charlie"));
+        set.add(60, ("eu", "Dave"));
+
+        let adjusted = set.adjust_scores_where(|&(region, _)| region == "eu", 50);
+        assert_eq!(adjusted, 2);
+        assert_eq!(set.get(10).unwrap(), vec![("us", "Bob")]);
+        assert_eq!(
+            set.get(60).unwrap(),
+            vec![("us", "Charlie"), ("eu", "Alice")],
+            "Moved items should merge into the existing score"
+        );
+        assert_eq!(
+            set.get(110).unwrap(),
+            vec![("eu", "Dave")],
+            "Items should only be adjusted once"
+        );
+        assert_eq!(set.sum_of_scores(), 10 + 60 + 60 + 110);
+    }
+
+    #[test]
+    fn adjust_scores_where_skips_overflow() {
+        let set = ScoredSortedSet::new();
+        set.add(i32::MAX - 1, "Alice".to_string());
+        set.add(0, "Bob".to_string());
+
+        assert_eq!(set.adjust_scores_where(|_| true, 5), 1);
+        assert_eq!(
+            set.get(i32::MAX - 1).unwrap(),
+            vec!["Alice".to_string()],
+            "An overflowing item should keep its score"
+        );
+        assert_eq!(set.get(5).unwrap(), vec!["Bob".to_string()]);
+    }
+
+    #[test]
+    fn into_sorted_vec_desc_reverses_everything() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        set.add(20, "Charlie".to_string());
+
+        assert_eq!(
+            set.into_sorted_vec_desc(),
+            vec![
+                (20, "Charlie".to_string()),
+                (20, "Bob".to_string()),
+                (10, "Alice".to_string()),
+            ],
+            "Scores and tied items should both be reversed"
+        );
+    }
+
+    #[test]
+    fn tie_count_excludes_item_itself() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(10, "Bob".to_string());
+        set.add(10, "Charlie".to_string());
+        set.add(20, "Dave".to_string());
+
+        assert_eq!(set.tie_count(&"Alice".to_string()), Some(2));
+        assert_eq!(set.tie_count(&"Dave".to_string()), Some(0));
+        assert_eq!(set.tie_count(&"Nobody".to_string()), None);
+    }
+
+    #[test]
+    fn content_hash_matches_equal_contents() {
+        let a = ScoredSortedSet::new();
+        a.add(10, "Alice".to_string());
+        a.add(20, "Bob".to_string());
+
+        let b = ScoredSortedSet::new();
+        b.add(20, "Bob".to_string());
+        b.add(10, "Alice".to_string());
+        assert_eq!(
+            a.content_hash(),
+            b.content_hash(),
+            "Insertion order across scores is irrelevant"
+        );
+
+        b.update_score(20, 21, &"Bob".to_string());
+        assert_ne!(
+            a.content_hash(),
+            b.content_hash(),
+            "A score change should change the hash"
+        );
+    }
+
+    #[test]
+    fn content_hash_includes_tie_order() {
+        let a = ScoredSortedSet::new();
+        a.add(10, "Alice".to_string());
+        a.add(10, "Bob".to_string());
+
+        let b = ScoredSortedSet::new();
+        b.add(10, "Bob".to_string());
+        b.add(10, "Alice".to_string());
+        assert_ne!(a.content_hash(), b.content_hash());
+    }
+
+    #[test]
+    fn group_best_rank_keeps_first_rank() {
+        let set = ScoredSortedSet::new();
+        set.add(30, ("red", "Alice"));
+        set.add(10, ("blue", "Bob"));
+        set.add(20, ("red", "Charlie"));
+        set.add(20, ("green", "Dave"));
+
+        let ranks = set.group_best_rank(|&(team, _)| team);
+        assert_eq!(ranks.len(), 3);
+        assert_eq!(ranks["blue"], 0);
+        assert_eq!(ranks["red"], 1, "Red's best member is Charlie at rank 1");
+        assert_eq!(
+            ranks["green"], 2,
+            "Ties hold distinct ranks in insertion order"
+        );
+    }
+
+    #[test]
+    fn move_to_top_and_bottom() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        set.add(30, "Charlie".to_string());
+
+        assert_eq!(set.move_to_top(&"Alice".to_string()), Some(31));
+        assert_eq!(
+            set.highest_score().unwrap(),
+            (31, vec!["Alice".to_string()])
+        );
+        assert_eq!(
+            set.move_to_top(&"Alice".to_string()),
+            Some(31),
+            "A sole leader should stay put"
+        );
+
+        assert_eq!(set.move_to_bottom(&"Charlie".to_string()), Some(19));
+        assert_eq!(
+            set.lowest_score().unwrap(),
+            (19, vec!["Charlie".to_string()])
+        );
+        assert_eq!(set.move_to_bottom(&"Nobody".to_string()), None);
+    }
+
+    #[test]
+    fn move_to_top_breaks_ties_and_handles_overflow() {
+        let set = ScoredSortedSet::new();
+        set.add(i32::MAX, "Alice".to_string());
+        set.add(i32::MAX, "Bob".to_string());
+
+        assert_eq!(
+            set.move_to_top(&"Bob".to_string()),
+            None,
+            "There is no score above i32::MAX"
+        );
+        assert_eq!(set.get(i32::MAX).unwrap().len(), 2, "Nothing should change");
+
+        let single = ScoredSortedSet::new();
+        single.add(5, "Alice".to_string());
+        assert_eq!(single.move_to_bottom(&"Alice".to_string()), Some(5));
+    }
+
+    #[test]
+    fn len_and_is_empty_empty_set() {
+        let set: ScoredSortedSet<String> = ScoredSortedSet::new();
+        assert_eq!(set.len(), 0);
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn len_counts_items_not_scores() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(10, "Bob".to_string());
+        set.add(20, "Charlie".to_string());
+
+        assert_eq!(
+            set.len(),
+            3,
+            "Every item at a shared score should be counted"
+        );
+        assert!(!set.is_empty());
+    }
+
+    #[test]
+    fn len_after_score_emptied_by_remove() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+
+        set.remove(10, &"Alice".to_string());
+        assert_eq!(set.len(), 1, "The emptied score should not count");
+        assert_eq!(set.all_scores(), vec![20]);
+
+        set.remove(20, &"Bob".to_string());
+        assert_eq!(set.len(), 0);
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn contains_searches_all_scores() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        set.add(30, "Bob".to_string());
+
+        assert!(set.contains(&"Alice".to_string()));
+        assert!(
+            set.contains(&"Bob".to_string()),
+            "Items under several scores are found"
+        );
+        assert!(!set.contains(&"Charlie".to_string()));
+    }
+
+    #[test]
+    fn contains_at_checks_one_score() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+
+        assert!(set.contains_at(10, &"Alice".to_string()));
+        assert!(!set.contains_at(20, &"Alice".to_string()));
+        assert!(
+            !set.contains_at(30, &"Alice".to_string()),
+            "Absent score contains nothing"
+        );
+    }
+
+    #[test]
+    fn score_index_holds_prefix_sums() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(10, "Bob".to_string());
+        set.add(20, "Charlie".to_string());
+        set.add(35, "Dave".to_string());
+
+        let index = set.score_index();
+        assert_eq!(index, vec![(10, 0), (20, 2), (35, 3)]);
+        assert_eq!(
+            index.binary_search_by_key(&20, |&(score, _)| score),
+            Ok(1),
+            "The index should be searchable by score"
+        );
+    }
+
+    #[test]
+    fn into_max_heap_pops_highest_first() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(30, "Bob".to_string());
+        set.add(30, "Charlie".to_string());
+
+        let mut heap = set.into_max_heap();
+        assert_eq!(
+            heap.pop(),
+            Some((30, "Charlie".to_string())),
+            "Ties pop by item order"
+        );
+        assert_eq!(heap.pop(), Some((30, "Bob".to_string())));
+        assert_eq!(heap.pop(), Some((10, "Alice".to_string())));
+        assert!(heap.is_empty());
+    }
+
+    #[test]
+    fn into_min_heap_pops_lowest_first() {
+        use std::cmp::Reverse;
+
+        let set = ScoredSortedSet::new();
+        set.add(30, "Alice".to_string());
+        set.add(10, "Charlie".to_string());
+        set.add(10, "Bob".to_string());
+
+        let mut heap = set.into_min_heap();
+        assert_eq!(heap.pop(), Some(Reverse((10, "Bob".to_string()))));
+        assert_eq!(heap.pop(), Some(Reverse((10, "Charlie".to_string()))));
+        assert_eq!(heap.pop(), Some(Reverse((30, "Alice".to_string()))));
+    }
+
+    #[test]
+    fn would_change_leader_compares_with_max() {
+        let set = ScoredSortedSet::new();
+        assert!(set.would_change_leader(0), "Any score leads an empty set");
+
+        set.add(10, "Alice".to_string());
+        set.add(20, "Bob".to_string());
+        assert!(set.would_change_leader(21));
+        assert!(
+            !set.would_change_leader(20),
+            "Tying the leader is not a new maximum"
+        );
+        assert!(!set.would_change_leader(15));
+        assert_eq!(set.len(), 2, "The set is not modified");
+    }
+
+    #[test]
+    fn count_in_range_exact_boundaries() {
+        let set = ScoredSortedSet::new();
+        set.add(100, "Alice".to_string());
+        set.add(150, "Bob".to_string());
+        set.add(150, "Charlie".to_string());
+        set.add(200, "Dave".to_string());
+        set.add(201, "Eve".to_string());
+
+        assert_eq!(
+            set.count_in_range(100, 200),
+            4,
+            "Boundary scores are included"
+        );
+        assert_eq!(set.count_in_range(150, 150), 2);
+    }
+
+    #[test]
+    fn count_in_range_empty_range() {
+        let set = ScoredSortedSet::new();
+        set.add(100, "Alice".to_string());
+
+        assert_eq!(set.count_in_range(101, 199), 0);
+        assert_eq!(
+            set.count_in_range(200, 100),
+            0,
+            "Inverted bounds count nothing"
+        );
+    }
+
+    #[test]
+    fn count_in_range_wider_than_all_scores() {
+        let set = ScoredSortedSet::new();
+        set.add(-50, "Alice".to_string());
+        set.add(100, "Bob".to_string());
+
+        assert_eq!(set.count_in_range(i32::MIN, i32::MAX), 2);
+    }
+
+    #[test]
+    fn map_rescore_filters_and_rescales() {
+        let set = ScoredSortedSet::new();
+        set.add(40, "Alice".to_string());
+        set.add(60, "Bob".to_string());
+        set.add(100, "Charlie".to_string());
+        set.add(101, "Dave".to_string());
+
+        let halved = set.map_rescore(|score, _| (score > 50).then_some(score / 2));
+        assert_eq!(halved.all_scores(), vec![30, 50]);
+        assert_eq!(
+            halved.get(50).unwrap(),
+            vec!["Charlie".to_string(), "Dave".to_string()],
+            "Items mapped to the same score should merge in order"
+        );
+        assert_eq!(halved.len(), 3);
+        assert_eq!(set.len(), 4, "The source set is unchanged");
+    }
+
+    #[test]
+    fn nth_largest_bucket_orders_by_size_then_score() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice");
+        set.add(20, "Bob");
+        set.add(20, "Charlie");
+        set.add(30, "Dave");
+        set.add(30, "Eve");
+        set.add(40, "Frank");
+        set.add(40, "Grace");
+        set.add(40, "Heidi");
+
+        assert_eq!(set.nth_largest_bucket(0), Some((40, 3)));
+        assert_eq!(
+            set.nth_largest_bucket(1),
+            Some((20, 2)),
+            "Size ties go to the lower score"
+        );
+        assert_eq!(set.nth_largest_bucket(2), Some((30, 2)));
+        assert_eq!(set.nth_largest_bucket(3), Some((10, 1)));
+        assert_eq!(set.nth_largest_bucket(4), None);
+        assert_eq!(ScoredSortedSet::<&str>::new().nth_largest_bucket(0), None);
+    }
+
+    #[test]
+    fn pop_highest_until_empty() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice");
+        set.add(30, "Bob");
+        set.add(30, "Charlie");
+        set.add(20, "Dave");
+
+        assert_eq!(set.pop_highest(), Some((30, "Charlie")));
+        assert_eq!(
+            set.get(30).unwrap(),
+            vec!["Bob"],
+            "The rest of the score's items stay"
+        );
+        assert_eq!(set.pop_highest(), Some((30, "Bob")));
+        assert!(set.get(30).is_none(), "Emptied score should be removed");
+        assert_eq!(set.pop_highest(), Some((20, "Dave")));
+        assert_eq!(set.pop_highest(), Some((10, "Alice")));
+        assert_eq!(set.pop_highest(), None);
+        assert!(set.is_empty());
+        assert_eq!(set.len(), 0);
+    }
+
+    #[test]
+    fn pop_lowest_until_empty() {
+        let set = ScoredSortedSet::with_cached_aggregates();
+        set.add(10, "Alice");
+        set.add(10, "Bob");
+        set.add(20, "Charlie");
+
+        assert_eq!(set.pop_lowest(), Some((10, "Alice")));
+        assert_eq!(set.get(10).unwrap(), vec!["Bob"]);
+        assert_eq!(set.sum_of_scores(), 30);
+        assert_eq!(set.pop_lowest(), Some((10, "Bob")));
+        assert_eq!(set.pop_lowest(), Some((20, "Charlie")));
+        assert_eq!(set.pop_lowest(), None);
+        assert_eq!(set.sum_of_scores(), 0);
+    }
+
+    #[test]
+    fn concurrent_pops_never_share_items() {
+        let set = std::sync::Arc::new(ScoredSortedSet::new());
+        for i in 0..1000 {
+            set.add(i % 10, i);
+        }
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let set = std::sync::Arc::clone(&set);
+                std::thread::spawn(move || {
+                    let mut popped = Vec::new();
+                    while let Some((_, item)) = set.pop_highest() {
+                        popped.push(item);
+                    }
+                    popped
+                })
+            })
+            .collect();
+
+        let mut all: Vec<i32> = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+        all.sort();
+        assert_eq!(all, (0..1000).collect::<Vec<_>>(), "Each item popped once");
+    }
+
+    #[test]
+    fn snapshot_with_version_matches_contents() {
+        let set = ScoredSortedSet::new();
+        assert_eq!(set.snapshot_with_version(), (vec![], 0));
+
+        set.add(20, "Bob");
+        set.add(10, "Alice");
+        let (items, version) = set.snapshot_with_version();
+        assert_eq!(items, vec![(10, "Alice"), (20, "Bob")]);
+        assert_eq!(version, set.version());
+
+        set.remove(20, &"Bob");
+        let (items, later) = set.snapshot_with_version();
+        assert_eq!(items, vec![(10, "Alice")]);
+        assert!(later > version, "A change should produce a newer version");
+    }
+
+    #[test]
+    fn rank_counts_lower_scores_and_ties() {
+        let set = ScoredSortedSet::new();
+        set.add(20, "Charlie");
+        set.add(10, "Alice");
+        set.add(20, "Dave");
+        set.add(10, "Bob");
+        set.add(30, "Eve");
+
+        assert_eq!(set.rank(&"Alice"), Some(0));
+        assert_eq!(set.rank(&"Bob"), Some(1), "Ties rank in insertion order");
+        assert_eq!(set.rank(&"Charlie"), Some(2));
+        assert_eq!(set.rank(&"Dave"), Some(3));
+        assert_eq!(set.rank(&"Eve"), Some(4));
+        assert_eq!(set.rank(&"Frank"), None);
+    }
+
+    #[test]
+    fn reverse_rank_mirrors_rank() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice");
+        set.add(20, "Bob");
+        set.add(20, "Charlie");
+
+        assert_eq!(set.reverse_rank(&"Alice"), Some(2));
+        assert_eq!(set.reverse_rank(&"Bob"), Some(1));
+        assert_eq!(
+            set.reverse_rank(&"Charlie"),
+            Some(0),
+            "The latest tied item is on top"
+        );
+        assert_eq!(set.reverse_rank(&"Dave"), None);
+        for name in ["Alice", "Bob", "Charlie"] {
+            assert_eq!(
+                set.reverse_rank(&name).unwrap(),
+                set.len() - 1 - set.rank(&name).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn unique_scores_reject_taken_scores() {
+        let set = ScoredSortedSet::with_unique_scores();
+        assert!(set.add(10, "Alice"));
+        assert!(!set.add(10, "Bob"), "Score 10 is already taken");
+        assert!(set.add(20, "Bob"));
+        assert_eq!(set.get(10).unwrap(), vec!["Alice"]);
+
+        assert!(!set.update_score(20, 10, &"Bob"));
+        assert!(set.update_score(20, 20, &"Bob"), "Staying put is allowed");
+        assert!(set.update_score(20, 30, &"Bob"));
+        assert_eq!(set.all_scores(), vec![10, 30]);
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn unique_scores_apply_to_bulk_updates() {
+        let set = ScoredSortedSet::with_unique_scores();
+        set.add(10, "Alice");
+        set.add(20, "Bob");
+
+        let applied = set.set_scores(&[("Charlie", 10), ("Bob", 20), ("Alice", 15)]);
+        assert_eq!(applied, 2, "Charlie cannot take Alice's score");
+        assert_eq!(set.all_scores(), vec![15, 20]);
+        assert!(!set.contains(&"Charlie"));
+
+        let rescored = set.map_rescore(|_, _| Some(0));
+        assert_eq!(
+            rescored.get(0).unwrap(),
+            vec!["Alice"],
+            "Later items collide"
+        );
+    }
+
+    #[test]
+    fn unique_scores_adjust_blocked_by_stationary_items() {
+        let set = ScoredSortedSet::with_unique_scores();
+        set.add(1, "Alice");
+        set.add(2, "Bob");
+        set.add(3, "Charlie");
+        set.add(5, "Dave");
+
+        // Everyone but Charlie moves up by one: Bob is blocked by Charlie, which in turn
+        // blocks Alice, while Dave moves freely.
+        let moved = set.adjust_scores_where(|item| *item != "Charlie", 1);
+        assert_eq!(moved, 1);
+        assert_eq!(set.all_scores(), vec![1, 2, 3, 6]);
+
+        // With everyone moving, the whole chain shifts together.
+        assert_eq!(set.adjust_scores_where(|_| true, -1), 4);
+        assert_eq!(set.all_scores(), vec![0, 1, 2, 5]);
+        assert_eq!(set.get(0).unwrap(), vec!["Alice"]);
+    }
+
+    #[test]
+    fn range_by_score_inclusive_window() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice");
+        set.add(20, "Bob");
+        set.add(20, "Charlie");
+        set.add(30, "Dave");
+
+        assert_eq!(
+            set.range_by_score(15, 30),
+            vec![(20, "Bob"), (20, "Charlie"), (30, "Dave")]
+        );
+        assert_eq!(
+            set.range_by_score(20, 20),
+            vec![(20, "Bob"), (20, "Charlie")],
+            "A single-score window"
+        );
+        assert!(set.range_by_score(11, 19).is_empty(), "An empty window");
+        assert!(
+            set.range_by_score(30, 10).is_empty(),
+            "Reversed bounds should return nothing"
+        );
+    }
+
+    #[test]
+    fn range_by_score_bounds_exclusive() {
+        use std::ops::Bound::{Excluded, Included, Unbounded};
+
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice");
+        set.add(20, "Bob");
+        set.add(30, "Charlie");
+
+        assert_eq!(
+            set.range_by_score_bounds(Excluded(10), Excluded(30)),
+            vec![(20, "Bob")]
+        );
+        assert_eq!(
+            set.range_by_score_bounds(Included(20), Unbounded),
+            vec![(20, "Bob"), (30, "Charlie")]
+        );
+        assert!(set
+            .range_by_score_bounds(Excluded(20), Excluded(20))
+            .is_empty());
+        assert!(set
+            .range_by_score_bounds(Included(20), Excluded(20))
+            .is_empty());
+        assert!(set
+            .range_by_score_bounds(Excluded(30), Included(10))
+            .is_empty());
+    }
+
+    #[test]
+    fn collect_ranked_into_reuses_buffer() {
+        let set = ScoredSortedSet::new();
+        set.add(20, "Bob");
+        set.add(10, "Alice");
+        set.add(20, "Charlie");
+
+        let mut buf = vec![(9, 9, "stale")];
+        set.collect_ranked_into(&mut buf);
+        assert_eq!(
+            buf,
+            vec![(0, 10, "Alice"), (1, 20, "Bob"), (2, 20, "Charlie")]
+        );
+
+        set.collect_ranked_into_desc(&mut buf);
+        assert_eq!(
+            buf,
+            vec![(0, 20, "Charlie"), (1, 20, "Bob"), (2, 10, "Alice")],
+            "Descending order reverses ties too"
+        );
+
+        ScoredSortedSet::new().collect_ranked_into(&mut buf);
+        assert!(buf.is_empty(), "The buffer is cleared first");
+    }
+
+    #[test]
+    fn into_iter_yields_ascending_with_ties_in_insertion_order() {
+        let set = ScoredSortedSet::new();
+        set.add(20, "Charlie".to_string());
+        set.add(10, "Alice".to_string());
+        set.add(20, "Dave".to_string());
+        set.add(10, "Bob".to_string());
+
+        let expected = vec![
+            (10, "Alice".to_string()),
+            (10, "Bob".to_string()),
+            (20, "Charlie".to_string()),
+            (20, "Dave".to_string()),
+        ];
+        assert_eq!(set.iter().collect::<Vec<_>>(), expected);
+        assert_eq!(set.into_iter().collect::<Vec<_>>(), expected);
+
+        let empty: ScoredSortedSet<String> = ScoredSortedSet::new();
+        assert_eq!(empty.into_iter().next(), None);
+    }
+
+    #[test]
+    fn into_iter_in_for_loop() {
+        let set = ScoredSortedSet::new();
+        set.add(2, "Bob");
+        set.add(1, "Alice");
+
+        let mut seen = Vec::new();
+        for (score, item) in set {
+            seen.push(format!("{}:{}", score, item));
+        }
+        assert_eq!(seen, vec!["1:Alice", "2:Bob"]);
+    }
+
+    #[test]
+    fn distinct_scores_in_range_counts_scores_not_items() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice");
+        set.add(20, "Bob");
+        set.add(20, "Charlie");
+        set.add(30, "Dave");
+
+        assert_eq!(set.distinct_scores_in_range(10, 20), 2);
+        assert_eq!(set.count_in_range(10, 20), 3);
+        assert_eq!(set.distinct_scores_in_range(i32::MIN, i32::MAX), 3);
+        assert_eq!(set.distinct_scores_in_range(21, 29), 0);
+        assert_eq!(set.distinct_scores_in_range(30, 10), 0, "Inverted bounds");
+    }
+
+    #[test]
+    fn clear_empties_and_set_stays_usable() {
+        let set = ScoredSortedSet::with_cached_aggregates();
+        set.add(10, "Alice");
+        set.add(20, "Bob");
+        set.add(20, "Charlie");
+        let version = set.version();
+
+        set.clear();
+        assert_eq!(set.len(), 0);
+        assert!(set.is_empty());
+        assert!(set.all_scores().is_empty());
+        assert_eq!(set.sum_of_scores(), 0);
+        assert!(set.version() > version);
+
+        assert!(set.add(5, "Dave"));
+        assert_eq!(set.get(5).unwrap(), vec!["Dave"]);
+        assert_eq!(set.len(), 1);
+        assert_eq!(set.sum_of_scores(), 5);
+    }
+
+    #[test]
+    fn modify_bucket_edits_one_score() {
+        let set = ScoredSortedSet::with_cached_aggregates();
+        set.add(10, "Charlie");
+        set.add(10, "Alice");
+        set.add(10, "Charlie");
+        set.add(20, "Bob");
+
+        let kept = set.modify_bucket(10, |items| {
+            items.sort();
+            items.dedup();
+            items.len()
+        });
+        assert_eq!(kept, Some(2));
+        assert_eq!(set.get(10).unwrap(), vec!["Alice", "Charlie"]);
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.sum_of_scores(), 40);
+
+        assert_eq!(set.modify_bucket(30, |items| items.len()), None);
+    }
+
+    #[test]
+    fn modify_bucket_removes_emptied_score() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice");
+        set.add(20, "Bob");
+
+        assert_eq!(set.modify_bucket(10, |items| items.clear()), Some(()));
+        assert_eq!(
+            set.all_scores(),
+            vec![20],
+            "Emptied score should be removed"
+        );
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn collect_matches_repeated_add() {
+        let pairs = vec![(20, "Bob"), (10, "Alice"), (20, "Charlie"), (10, "Dave")];
+        let collected: ScoredSortedSet<&str> = pairs.iter().copied().collect();
+
+        let added = ScoredSortedSet::new();
+        for (score, item) in pairs {
+            added.add(score, item);
+        }
+        assert_eq!(collected.all_scores(), added.all_scores());
+        for score in added.all_scores() {
+            assert_eq!(collected.get(score), added.get(score));
+        }
+        assert_eq!(collected.get(20).unwrap(), vec!["Bob", "Charlie"]);
+        assert_eq!(collected.len(), 4);
+
+        let empty: ScoredSortedSet<&str> = std::iter::empty().collect();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn extend_appends_to_existing_items() {
+        let mut set = ScoredSortedSet::with_score_bounds(0, 100);
+        set.add(10, "Alice");
+        set.add(20, "Bob");
+
+        set.extend(vec![
+            (10, "Charlie"),
+            (30, "Dave"),
+            (200, "Eve"),
+            (10, "Frank"),
+        ]);
+        assert_eq!(set.get(10).unwrap(), vec!["Alice", "Charlie", "Frank"]);
+        assert_eq!(set.get(20).unwrap(), vec!["Bob"]);
+        assert_eq!(set.get(30).unwrap(), vec!["Dave"]);
+        assert!(set.get(200).is_none(), "Out-of-bounds pairs are skipped");
+        assert_eq!(set.len(), 5);
+    }
+
+    #[test]
+    fn increment_score_moves_item() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice");
+        set.add(20, "Bob");
+
+        assert_eq!(set.increment_score(&"Alice", 15), Some(25));
+        assert_eq!(set.score_of(&"Alice"), Some(25));
+        assert!(set.get(10).is_none());
+        assert_eq!(set.increment_score(&"Bob", -30), Some(-10));
+        assert_eq!(set.increment_score(&"Charlie", 1), None);
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn increment_score_rejects_overflow() {
+        let set = ScoredSortedSet::new();
+        set.add(i32::MAX - 1, "Alice");
+        set.add(i32::MIN, "Bob");
+
+        assert_eq!(set.increment_score(&"Alice", 1), Some(i32::MAX));
+        assert_eq!(set.increment_score(&"Alice", 1), None, "Would overflow");
+        assert_eq!(set.score_of(&"Alice"), Some(i32::MAX), "Left in place");
+        assert_eq!(set.increment_score(&"Bob", -1), None);
+        assert_eq!(set.score_of(&"Bob"), Some(i32::MIN));
+    }
+
+    #[test]
+    fn increment_or_insert_adds_missing_items() {
+        let set = ScoredSortedSet::with_score_bounds(0, 100);
+        assert_eq!(set.increment_or_insert("Alice", 5), Some(5));
+        assert_eq!(set.increment_or_insert("Alice", 5), Some(10));
+        assert_eq!(set.increment_or_insert("Alice", 95), None, "Out of bounds");
+        assert_eq!(set.increment_or_insert("Bob", -1), None);
+        assert_eq!(set.get(10).unwrap(), vec!["Alice"]);
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn concurrent_increments_are_not_lost() {
+        let set = std::sync::Arc::new(ScoredSortedSet::new());
+        set.add(0, "counter");
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let set = std::sync::Arc::clone(&set);
+                std::thread::spawn(move || {
+                    for _ in 0..250 {
+                        set.increment_score(&"counter", 1).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(set.score_of(&"counter"), Some(1000));
+    }
+
+    #[test]
+    fn remove_item_finds_the_score() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice");
+        set.add(20, "Bob");
+
+        assert_eq!(set.remove_item(&"Bob"), Some(20));
+        assert!(set.get(20).is_none(), "Emptied score should be removed");
+        assert_eq!(set.remove_item(&"Bob"), None);
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn remove_item_takes_lowest_occurrence_first() {
+        let set = ScoredSortedSet::new();
+        set.add(30, "Alice");
+        set.add(10, "Alice");
+        set.add(10, "Bob");
+        set.add(10, "Alice");
+
+        assert_eq!(set.remove_item(&"Alice"), Some(10));
+        assert_eq!(set.get(10).unwrap(), vec!["Bob", "Alice"]);
+        assert_eq!(set.remove_item(&"Alice"), Some(10));
+        assert_eq!(set.score_of(&"Alice"), Some(30));
+        assert_eq!(set.remove_item(&"Alice"), Some(30));
+        assert_eq!(set.remove_item(&"Alice"), None);
+        assert_eq!(set.all_scores(), vec![10]);
+    }
+
+    #[test]
+    fn add_unique_keeps_one_copy() {
+        let set = ScoredSortedSet::new();
+        assert!(set.add_unique(10, "Alice"));
+        assert!(!set.add_unique(10, "Alice"), "Same score");
+        assert!(!set.add_unique(20, "Alice"), "Different score");
+        assert!(set.add_unique(10, "Bob"));
+        assert_eq!(set.get(10).unwrap(), vec!["Alice", "Bob"]);
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn add_or_update_moves_existing_item() {
+        let set = ScoredSortedSet::new();
+        assert!(set.add_or_update(10, "Alice"));
+        set.add(10, "Bob");
+        assert!(set.add_or_update(30, "Alice"));
+        assert_eq!(set.get(10).unwrap(), vec!["Bob"]);
+        assert_eq!(set.get(30).unwrap(), vec!["Alice"]);
+
+        assert!(set.add_or_update(10, "Alice"));
+        assert_eq!(
+            set.get(10).unwrap(),
+            vec!["Bob", "Alice"],
+            "Moved items go to the end"
+        );
+        assert_eq!(set.len(), 2);
+    }
+
+    #[cfg(feature = "parking_lot")]
+    #[test]
+    fn panic_under_lock_does_not_poison() {
+        let set = std::sync::Arc::new(ScoredSortedSet::new());
+        set.add(10, "Alice");
+        set.add(20, "Bob");
+
+        let panicking = std::sync::Arc::clone(&set);
+        let result = std::thread::spawn(move || {
+            panicking.modify_bucket(10, |_| panic!("boom"));
+        })
+        .join();
+        assert!(result.is_err());
+
+        // The items being modified survive the panic, and the set stays usable and consistent
+        assert_eq!(set.get(10).unwrap(), vec!["Alice"]);
+        assert!(set.add(30, "Charlie"));
+        assert_eq!(set.all_scores(), vec![10, 20, 30]);
+        assert_eq!(set.len(), 3);
+    }
+
+    #[test]
+    fn capacity_per_score_applies_to_new_scores() {
+        let set = ScoredSortedSet::with_capacity_per_score(16);
+        for i in 0..1000 {
+            set.add(7, i);
+        }
+        set.add(8, -1);
+        assert_eq!(set.get(7).unwrap(), (0..1000).collect::<Vec<_>>());
+        assert_eq!(set.len(), 1001);
+
+        let inner = set.raw_read();
+        assert!(inner[&8].capacity() >= 16, "New scores start with the hint");
+    }
+
+    #[test]
+    fn union_merges_overlapping_scores() {
+        let east = ScoredSortedSet::new();
+        east.add(10, "Alice");
+        east.add(20, "Bob");
+        let west = ScoredSortedSet::new();
+        west.add(20, "Charlie");
+        west.add(30, "Alice");
+
+        let global = east.union(&west);
+        assert_eq!(global.all_scores(), vec![10, 20, 30]);
+        assert_eq!(
+            global.get(20).unwrap(),
+            vec!["Bob", "Charlie"],
+            "This set's items come first"
+        );
+        assert_eq!(global.len(), 4, "Alice is kept at both scores");
+        assert_eq!(east.len(), 2, "The inputs are unchanged");
+    }
+
+    #[test]
+    fn union_of_disjoint_and_same_sets() {
+        let low = ScoredSortedSet::with_score_bounds(0, 50);
+        low.add(1, "Alice");
+        let high = ScoredSortedSet::new();
+        high.add(40, "Bob");
+        high.add(90, "Charlie");
+
+        let merged = low.union(&high);
+        assert_eq!(
+            merged.all_scores(),
+            vec![1, 40],
+            "Outside this set's bounds"
+        );
+
+        let doubled = low.union(&low);
+        assert_eq!(doubled.get(1).unwrap(), vec!["Alice", "Alice"]);
+    }
+
+    #[test]
+    fn intersection_by_item_keeps_own_scores() {
+        let season = ScoredSortedSet::new();
+        season.add(10, "Alice");
+        season.add(20, "Bob");
+        season.add(20, "Charlie");
+        let weekly = ScoredSortedSet::new();
+        weekly.add(99, "Charlie");
+        weekly.add(5, "Alice");
+        weekly.add(7, "Dave");
+
+        let both = season.intersection_by_item(&weekly);
+        assert_eq!(both.all_scores(), vec![10, 20], "Scores come from self");
+        assert_eq!(both.get(20).unwrap(), vec!["Charlie"]);
+        assert_eq!(both.len(), 2);
+
+        let full = season.intersection_by_item(&season);
+        assert_eq!(full.len(), 3, "Full overlap keeps everything");
+        assert_eq!(full.get(20).unwrap(), vec!["Bob", "Charlie"]);
+    }
+
+    #[test]
+    fn intersection_by_item_of_disjoint_sets_is_empty() {
+        let a = ScoredSortedSet::new();
+        a.add(10, "Alice");
+        let b = ScoredSortedSet::new();
+        b.add(10, "Bob");
+
+        let none = a.intersection_by_item(&b);
+        assert!(none.is_empty());
+        assert!(none.all_scores().is_empty());
+    }
+
+    #[test]
+    fn weighted_union_sums_weighted_scores() {
+        let kills = ScoredSortedSet::new();
+        kills.add(10, "Alice");
+        kills.add(4, "Bob");
+        let assists = ScoredSortedSet::new();
+        assists.add(3, "Bob");
+        assists.add(5, "Charlie");
+
+        let combined = ScoredSortedSet::weighted_union(&[(&kills, 3), (&assists, 2)]);
+        assert_eq!(combined.score_of(&"Alice"), Some(30));
+        assert_eq!(combined.score_of(&"Bob"), Some(18), "4 * 3 + 3 * 2");
+        assert_eq!(combined.score_of(&"Charlie"), Some(10));
+        assert_eq!(combined.len(), 3, "Overlapping items appear once");
+
+        let negated = ScoredSortedSet::weighted_union(&[(&kills, -1)]);
+        assert_eq!(negated.all_scores(), vec![-10, -4]);
+    }
+
+    #[test]
+    fn weighted_union_saturates() {
+        let set = ScoredSortedSet::new();
+        set.add(i32::MAX, "Alice");
+        set.add(i32::MIN, "Bob");
+
+        let doubled = ScoredSortedSet::weighted_union(&[(&set, 2), (&set, 1)]);
+        assert_eq!(doubled.score_of(&"Alice"), Some(i32::MAX));
+        assert_eq!(doubled.score_of(&"Bob"), Some(i32::MIN));
+        assert!(ScoredSortedSet::<&str>::weighted_union(&[]).is_empty());
+    }
+
+    #[test]
+    fn snapshot_is_unaffected_by_later_changes() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice");
+        set.add(10, "Bob");
+
+        let snapshot = set.snapshot();
+        set.add(20, "Charlie");
+        set.remove(10, &"Alice");
+
+        assert_eq!(snapshot, BTreeMap::from([(10, vec!["Alice", "Bob"])]));
+        assert_eq!(set.all_scores(), vec![10, 20]);
+    }
+
+    #[test]
+    fn retain_by_score_threshold() {
+        let set = ScoredSortedSet::with_cached_aggregates();
+        set.add(-5, "Alice");
+        set.add(-1, "Bob");
+        set.add(0, "Charlie");
+        set.add(7, "Dave");
+
+        set.retain(|score, _| score >= 0);
+        assert_eq!(set.all_scores(), vec![0, 7], "Emptied scores disappear");
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.sum_of_scores(), 7);
+    }
+
+    #[test]
+    fn retain_by_item_property() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "stale:Alice");
+        set.add(10, "Bob");
+        set.add(20, "stale:Charlie");
+
+        let mut seen = Vec::new();
+        set.retain(|score, item| {
+            seen.push(score);
+            !item.starts_with("stale:")
+        });
+        assert_eq!(seen, vec![10, 10, 20], "Visited in ascending order");
+        assert_eq!(set.all_scores(), vec![10]);
+        assert_eq!(set.get(10).unwrap(), vec!["Bob"]);
+    }
+
+    #[test]
+    fn get_by_rank_walks_ties() {
+        let set = ScoredSortedSet::new();
+        set.add(20, "Charlie");
+        set.add(10, "Alice");
+        set.add(10, "Bob");
+        set.add(20, "Dave");
+
+        assert_eq!(set.get_by_rank(0), Some((10, "Alice")));
+        assert_eq!(set.get_by_rank(1), Some((10, "Bob")));
+        assert_eq!(set.get_by_rank(2), Some((20, "Charlie")));
+        assert_eq!(set.get_by_rank(3), Some((20, "Dave")));
+        assert_eq!(set.get_by_rank(4), None);
+        for rank in 0..4 {
+            let (_, item) = set.get_by_rank(rank).unwrap();
+            assert_eq!(set.rank(&item), Some(rank), "Inverse of rank");
+        }
+    }
+
+    #[test]
+    fn get_by_reverse_rank_walks_ties() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice");
+        set.add(20, "Bob");
+        set.add(20, "Charlie");
+
+        assert_eq!(set.get_by_reverse_rank(0), Some((20, "Charlie")));
+        assert_eq!(set.get_by_reverse_rank(1), Some((20, "Bob")));
+        assert_eq!(set.get_by_reverse_rank(2), Some((10, "Alice")));
+        assert_eq!(set.get_by_reverse_rank(3), None);
+        assert_eq!(set.get_by_reverse_rank(usize::MAX), None);
+        assert_eq!(ScoredSortedSet::<&str>::new().get_by_reverse_rank(0), None);
+    }
+
+    #[test]
+    fn remove_range_by_score_middle_band() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice");
+        set.add(20, "Bob");
+        set.add(20, "Charlie");
+        set.add(30, "Dave");
+        set.add(40, "Eve");
+
+        assert_eq!(set.remove_range_by_score(15, 30), 3);
+        assert_eq!(set.all_scores(), vec![10, 40], "Surrounding scores stay");
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.remove_range_by_score(11, 39), 0, "An empty band");
+        assert_eq!(set.remove_range_by_score(40, 10), 0, "Inverted bounds");
+        assert_eq!(set.all_scores(), vec![10, 40]);
+    }
+
+    #[test]
+    fn remove_range_by_score_full_range() {
+        let set = ScoredSortedSet::new();
+        set.add(i32::MIN, "Alice");
+        set.add(0, "Bob");
+        set.add(i32::MAX, "Charlie");
+
+        assert_eq!(set.remove_range_by_score(i32::MIN, i32::MAX), 3);
+        assert!(set.is_empty());
+        assert_eq!(set.len(), 0);
+    }
+
+    #[test]
+    fn pop_n_highest_crosses_scores() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice");
+        set.add(20, "Bob");
+        set.add(30, "Charlie");
+        set.add(30, "Dave");
+        set.add(30, "Eve");
+
+        assert_eq!(set.pop_n_highest(2), vec![(30, "Eve"), (30, "Dave")]);
+        assert_eq!(set.get(30).unwrap(), vec!["Charlie"]);
+        assert_eq!(
+            set.pop_n_highest(2),
+            vec![(30, "Charlie"), (20, "Bob")],
+            "Emptied scores are removed"
+        );
+        assert_eq!(set.all_scores(), vec![10]);
+        assert!(set.pop_n_highest(0).is_empty());
+    }
+
+    #[test]
+    fn pop_n_lowest_more_than_exists() {
+        let set = ScoredSortedSet::with_cached_aggregates();
+        set.add(20, "Charlie");
+        set.add(10, "Alice");
+        set.add(10, "Bob");
+
+        assert_eq!(set.pop_n_lowest(1), vec![(10, "Alice")]);
+        assert_eq!(
+            set.pop_n_lowest(10),
+            vec![(10, "Bob"), (20, "Charlie")],
+            "Everything left is returned"
+        );
+        assert!(set.is_empty());
+        assert_eq!(set.len(), 0);
+        assert_eq!(set.sum_of_scores(), 0);
+        assert!(set.pop_n_lowest(3).is_empty());
+    }
+
+    #[test]
+    fn pop_n_highest_matches_repeated_pops() {
+        let batched = ScoredSortedSet::new();
+        let single = ScoredSortedSet::new();
+        for i in 0..20 {
+            batched.add(i % 4, i);
+            single.add(i % 4, i);
+        }
+        let expected: Vec<_> = (0..7).map_while(|_| single.pop_highest()).collect();
+        assert_eq!(batched.pop_n_highest(7), expected);
+        let expected: Vec<_> = (0..7).map_while(|_| single.pop_lowest()).collect();
+        assert_eq!(batched.pop_n_lowest(7), expected);
+    }
+
+    #[test]
+    fn percentile_extremes_and_midpoint() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice");
+        set.add(20, "Bob");
+        set.add(20, "Charlie");
+        set.add(20, "Dave");
+        set.add(90, "Eve");
+
+        assert_eq!(set.percentile(0.0), Some(10), "q = 0 is the lowest score");
+        assert_eq!(set.percentile(1.0), Some(90), "q = 1 is the highest score");
+        assert_eq!(set.percentile(0.5), Some(20), "Duplicates weigh per item");
+        assert_eq!(set.percentile(0.2), Some(10));
+        assert_eq!(set.percentile(0.21), Some(20));
+        assert_eq!(set.percentile(0.8), Some(20));
+        assert_eq!(set.percentile(0.81), Some(90));
+    }
+
+    #[test]
+    fn percentile_rejects_bad_input() {
+        let set = ScoredSortedSet::new();
+        assert_eq!(set.percentile(0.5), None, "Empty set");
+
+        set.add(10, "Alice");
+        assert_eq!(set.percentile(-0.1), None);
+        assert_eq!(set.percentile(1.5), None);
+        assert_eq!(set.percentile(f64::NAN), None);
+        assert_eq!(set.percentile(0.99), Some(10));
     }
 
     #[test]
-    fn test_get_nonexistent() {
-        let set: ScoredSortedSet<i32> = ScoredSortedSet::new();
+    fn add_many_matches_repeated_add() {
+        let pairs = vec![(20, "Bob"), (10, "Alice"), (20, "Charlie"), (10, "Dave")];
+        let batched = ScoredSortedSet::with_cached_aggregates();
+        let looped = ScoredSortedSet::with_cached_aggregates();
+        looped.add(5, "Eve");
+        batched.add(5, "Eve");
 
-        // Attempt to get items for a score that has no items
-        let items = set.get(25);
-        assert!(items.is_none());
+        assert_eq!(batched.add_many(pairs.clone()), 4);
+        for (score, item) in pairs {
+            looped.add(score, item);
+        }
+        assert_eq!(batched.snapshot(), looped.snapshot());
+        assert_eq!(batched.len(), looped.len());
+        assert_eq!(batched.sum_of_scores(), looped.sum_of_scores());
     }
 
     #[test]
-    fn test_multiple_scores() {
-        let set: ScoredSortedSet<String> = ScoredSortedSet::new();
-        set.add(30, "Fred".to_string());
-        set.add(40, "George".to_string());
+    fn add_many_skips_rejected_pairs() {
+        let set = ScoredSortedSet::with_unique_scores();
+        let added = set.add_many([(1, "Alice"), (1, "Bob"), (2, "Charlie")]);
+        assert_eq!(added, 2, "Bob cannot share Alice's score");
+        assert_eq!(set.get(1).unwrap(), vec!["Alice"]);
+    }
 
-        let items_30 = set.get(30).unwrap();
-        assert_eq!(items_30, vec!["Fred".to_string()]);
+    #[test]
+    fn remove_many_counts_removed_items() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice");
+        set.add(10, "Bob");
+        set.add(20, "Charlie");
+        set.add(30, "Dave");
 
-        let items_40 = set.get(40).unwrap();
-        assert_eq!(items_40, vec!["George".to_string()]);
+        let removed = set.remove_many(&[
+            (10, "Alice"),
+            (20, "Charlie"),
+            (20, "Nobody"),
+            (99, "Dave"),
+            (20, "Charlie"),
+        ]);
+        assert_eq!(removed, 2, "Missing pairs remove nothing");
+        assert_eq!(set.all_scores(), vec![10, 30], "Emptied scores disappear");
+        assert_eq!(set.get(10).unwrap(), vec!["Bob"]);
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.remove_many(&[]), 0);
     }
 
     #[test]
-    fn test_multiple_items_same_score() {
-        let set: ScoredSortedSet<String> = ScoredSortedSet::new();
-        set.add(50, "Hannah".to_string());
-        set.add(50, "Ian".to_string());
+    fn timestamp_scores_use_i64() {
+        let set = ScoredSortedSet::<&str, i64>::with_score_type();
+        let now = 1_700_000_000_000_i64; // Milliseconds, well past i32::MAX
+        set.add(now, "Alice");
+        set.add(now + 5, "Bob");
+        set.add(now - 5, "Charlie");
 
-        let items = set.get(50).unwrap();
-        assert_eq!(items, vec!["Hannah".to_string(), "Ian".to_string()]);
+        assert_eq!(set.all_scores(), vec![now - 5, now, now + 5]);
+        assert_eq!(set.highest_scores(1), vec![(now + 5, vec!["Bob"])]);
+        assert_eq!(set.range_by_score(now, now + 5).len(), 2);
+        assert!(set.update_score(now, now + 10, &"Alice"));
+        assert_eq!(set.rank(&"Alice"), Some(2));
     }
 
     #[test]
-    fn update_score_existing_item() {
-        let set = ScoredSortedSet::new();
-        set.add(10, "Alice".to_string());
+    fn custom_ordered_scores() {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+        struct Priority {
+            level: u8,
+            deadline: u32,
+        }
 
-        set.update_score(10, 20, &"Alice".to_string());
+        let low = Priority {
+            level: 1,
+            deadline: 50,
+        };
+        let urgent = Priority {
+            level: 3,
+            deadline: 10,
+        };
+        let later = Priority {
+            level: 3,
+            deadline: 20,
+        };
+        let set = ScoredSortedSet::with_score_type();
+        set.add(later, "Report");
+        set.add(low, "Cleanup");
+        set.add(urgent, "Outage");
 
-        assert!(
-            set.get(10).is_none(),
-            "Item should be removed from the old score"
-        );
         assert_eq!(
-            set.get(20).unwrap(),
-            vec!["Alice".to_string()],
-            "Item should exist with the new score"
+            set.all_scores(),
+            vec![low, urgent, later],
+            "Fields compare in order"
         );
+        assert_eq!(set.pop_lowest(), Some((low, "Cleanup")));
+        let collected: Vec<_> = set.into_iter().collect();
+        assert_eq!(collected, vec![(urgent, "Outage"), (later, "Report")]);
     }
 
     #[test]
-    fn update_score_nonexistent_item() {
-        let set = ScoredSortedSet::new();
-        set.add(10, "Alice".to_string());
+    fn reversed_scores_order_descending() {
+        use std::cmp::Reverse;
 
-        // Attempt to update score of an item that doesn't exist at the specified old score
-        set.update_score(15, 25, &"Bob".to_string());
+        let set: ScoredSortedSet<&str, Reverse<i32>> =
+            [(Reverse(10), "Alice"), (Reverse(30), "Bob")]
+                .into_iter()
+                .collect();
+        assert_eq!(set.get_by_rank(0), Some((Reverse(30), "Bob")));
+        assert_eq!(set.lowest_scores(1), vec![(Reverse(30), vec!["Bob"])]);
+    }
 
-        assert!(set.get(15).is_none(), "Old score should not have any items");
-        assert!(
-            set.get(25).is_none(),
-            "New score should not have the item because it didn't exist at the old score"
+    #[test]
+    fn first_and_last_item_break_ties_by_insertion() {
+        let set = ScoredSortedSet::new();
+        for i in 0..1000 {
+            set.add(1, i);
+            set.add(9, i + 1000);
+        }
+        set.add(5, -1);
+
+        assert_eq!(
+            set.first_item(),
+            Some((1, 0)),
+            "Earliest added at the lowest score"
         );
         assert_eq!(
-            set.get(10).unwrap(),
-            vec!["Alice".to_string()],
-            "Other items should remain unaffected"
+            set.last_item(),
+            Some((9, 1999)),
+            "Latest added at the highest score"
         );
+        assert_eq!(set.last_item(), set.pop_highest());
+        assert_eq!(set.len(), 2000, "Peeking removes nothing");
     }
 
     #[test]
-    fn update_score_to_existing_score() {
-        let set = ScoredSortedSet::new();
-        set.add(10, "Alice".to_string());
-        set.add(20, "Bob".to_string());
+    fn first_and_last_item_of_empty_set() {
+        let set: ScoredSortedSet<&str> = ScoredSortedSet::new();
+        assert_eq!(set.first_item(), None);
+        assert_eq!(set.last_item(), None);
 
-        // Move Alice to the same score as Bob
-        set.update_score(10, 20, &"Alice".to_string());
+        set.add(3, "Alice");
+        assert_eq!(set.first_item(), set.last_item());
+    }
 
-        assert!(
-            set.get(10).is_none(),
-            "Alice should be removed from the old score"
-        );
-        let items_with_new_score = set.get(20).unwrap();
-        assert!(
-            items_with_new_score.contains(&"Alice".to_string()),
-            "Alice should be added to the new score"
-        );
-        assert!(
-            items_with_new_score.contains(&"Bob".to_string()),
-            "Bob should remain at the new score"
-        );
+    #[test]
+    fn move_score_appends_after_existing_items() {
+        let set = ScoredSortedSet::with_cached_aggregates();
+        set.add(0, "Alice");
+        set.add(0, "Bob");
+        set.add(1, "Charlie");
+
+        assert!(set.move_score(0, 1));
+        assert_eq!(set.get(1).unwrap(), vec!["Charlie", "Alice", "Bob"]);
+        assert!(set.get(0).is_none(), "The old score is removed");
+        assert_eq!(set.sum_of_scores(), 3);
+        assert_eq!(set.len(), 3);
     }
 
     #[test]
-    fn maintain_order_after_update_score() {
+    fn move_score_into_empty_slot() {
         let set = ScoredSortedSet::new();
-        set.add(10, "Alice".to_string());
-        set.add(10, "Bob".to_string());
+        set.add(5, "Alice");
+        set.add(5, "Bob");
 
-        // Update score of Alice, moving her to a new score
-        set.update_score(10, 20, &"Alice".to_string());
+        assert!(set.move_score(5, -5));
+        assert_eq!(set.all_scores(), vec![-5]);
+        assert_eq!(set.get(-5).unwrap(), vec!["Alice", "Bob"]);
+        assert!(!set.move_score(-5, -5), "Same score is a no-op");
+        assert!(!set.move_score(7, 8), "Nothing at the old score");
+        assert_eq!(set.get(-5).unwrap(), vec!["Alice", "Bob"]);
+    }
+
+    #[test]
+    fn move_score_respects_bounds_and_unique_scores() {
+        let bounded = ScoredSortedSet::with_score_bounds(0, 10);
+        bounded.add(5, "Alice");
+        assert!(!bounded.move_score(5, 11));
+        assert_eq!(bounded.all_scores(), vec![5]);
+
+        let unique = ScoredSortedSet::with_unique_scores();
+        unique.add(1, "Alice");
+        unique.add(2, "Bob");
+        assert!(!unique.move_score(1, 2), "Score 2 is taken");
+        assert!(unique.move_score(1, 3));
+        assert_eq!(unique.all_scores(), vec![2, 3]);
+    }
+
+    #[test]
+    fn drain_moves_everything_out_in_order() {
+        let set = ScoredSortedSet::with_cached_aggregates();
+        set.add(20, "Bob");
+        set.add(10, "Alice");
+        set.add(20, "Alice");
+        set.add(20, "Bob");
+        let version = set.version();
 
-        let items_with_old_score = set.get(10).unwrap();
         assert_eq!(
-            items_with_old_score,
-            vec!["Bob".to_string()],
-            "Only Bob should remain at the old score"
+            set.drain(),
+            vec![(10, "Alice"), (20, "Bob"), (20, "Alice"), (20, "Bob")],
+            "Duplicates within a score keep their insertion order"
         );
+        assert!(set.is_empty());
+        assert!(set.all_scores().is_empty());
+        assert_eq!(set.sum_of_scores(), 0);
+        assert!(set.version() > version);
+        assert!(set.drain().is_empty());
+    }
+
+    #[test]
+    fn debug_prints_scores_in_order() {
+        let set = ScoredSortedSet::new();
+        set.add(20, "Bob");
+        set.add(10, "Alice");
+        set.add(20, "Charlie");
 
-        let items_with_new_score = set.get(20).unwrap();
         assert_eq!(
-            items_with_new_score,
-            vec!["Alice".to_string()],
-            "Alice should be present with the new score"
+            format!("{:?}", set),
+            r#"ScoredSortedSet { scores: {10: ["Alice"], 20: ["Bob", "Charlie"]} }"#
         );
     }
 
     #[test]
-    fn highest_scores_more_than_exists() {
+    fn debug_does_not_block_on_a_held_lock() {
         let set = ScoredSortedSet::new();
-        set.add(10, "Alice".to_string());
-        set.add(20, "Bob".to_string());
+        set.add(10, "Alice");
 
-        // Request more scores than exist in the set
-        let scores = set.highest_scores(5);
-        assert_eq!(scores.len(), 2, "Should return only the available scores");
-        assert_eq!(scores[0].0, 20, "The highest score should be first");
-        assert_eq!(scores[1].0, 10, "The next highest score should be second");
+        let guard = set.raw_write();
+        assert_eq!(format!("{:?}", set), "ScoredSortedSet { scores: <locked> }");
+        drop(guard);
+        assert!(format!("{:?}", set).contains("Alice"));
     }
 
+    #[cfg(not(feature = "parking_lot"))]
     #[test]
-    fn highest_scores_exact_number() {
-        let set = ScoredSortedSet::new();
-        set.add(10, "Alice".to_string());
-        set.add(20, "Bob".to_string());
-        set.add(30, "Charlie".to_string());
+    fn debug_reads_a_poisoned_lock() {
+        let set = std::sync::Arc::new(ScoredSortedSet::new());
+        set.add(20, "Bob");
 
-        // Request exactly the number of scores that exist
-        let scores = set.highest_scores(3);
-        assert_eq!(scores.len(), 3, "Should return all available scores");
-        assert_eq!(scores[0].0, 30, "The highest score should be first");
-        assert_eq!(scores[1].0, 20, "The next highest score should be second");
-        assert_eq!(scores[2].0, 10, "The lowest score should be last");
+        let panicking = std::sync::Arc::clone(&set);
+        let result = std::thread::spawn(move || {
+            let _guard = panicking.raw_write();
+            panic!("boom");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(set.inner.is_poisoned());
+        assert_eq!(
+            format!("{:?}", set),
+            r#"ScoredSortedSet { scores: {20: ["Bob"]} }"#
+        );
     }
 
     #[test]
-    fn highest_scores_ordered_correctly() {
-        let set = ScoredSortedSet::new();
-        set.add(10, "Alice".to_string());
-        set.add(30, "Charlie".to_string());
-        set.add(20, "Bob".to_string());
+    fn default_is_empty() {
+        #[derive(Default)]
+        struct Leaderboard {
+            scores: ScoredSortedSet<String>,
+        }
 
-        // Request scores to verify they are ordered correctly
-        let scores = set.highest_scores(2);
-        assert_eq!(scores.len(), 2, "Should return the top 2 scores");
-        assert_eq!(scores[0].0, 30, "The highest score should be first");
-        assert_eq!(scores[1].0, 20, "The second highest score should be second");
+        let set = ScoredSortedSet::<String>::default();
+        assert!(set.is_empty());
+        assert!(set.all_scores().is_empty());
+        assert!(Leaderboard::default().scores.add(10, "Alice".to_string()));
     }
 
     #[test]
-    fn highest_scores_none_available() {
-        let set: ScoredSortedSet<String> = ScoredSortedSet::new();
+    fn to_sorted_vec_flattens_in_order() {
+        let set = ScoredSortedSet::new();
+        set.add(20, "Bob");
+        set.add(10, "Alice");
+        set.add(20, "Charlie");
+        set.add(20, "Bob");
+        set.add(-5, "Dave");
 
-        // Request scores when none are available
-        let scores = set.highest_scores(2);
-        assert!(
-            scores.is_empty(),
-            "Should return an empty vector when no scores are available"
+        let ascending = set.to_sorted_vec();
+        assert_eq!(
+            ascending,
+            vec![
+                (-5, "Dave"),
+                (10, "Alice"),
+                (20, "Bob"),
+                (20, "Charlie"),
+                (20, "Bob")
+            ]
         );
+
+        let mut descending = set.to_sorted_vec_desc();
+        assert_eq!(descending[0], (20, "Bob"));
+        assert_eq!(descending[1], (20, "Charlie"), "Ties come latest first");
+        descending.reverse();
+        assert_eq!(descending, ascending, "Exact reverses of each other");
     }
 
     #[test]
-    fn lowest_and_highest_score_empty_set() {
-        let set: ScoredSortedSet<String> = ScoredSortedSet::new();
+    fn to_sorted_vec_of_empty_set() {
+        let set: ScoredSortedSet<&str> = ScoredSortedSet::new();
+        assert!(set.to_sorted_vec().is_empty());
+        assert!(set.to_sorted_vec_desc().is_empty());
+    }
+
+    #[test]
+    fn modify_bucket_keeps_insertion_records_for_reads() {
+        let set = ScoredSortedSet::with_insertion_tracking();
+        set.add(1, "x");
+        set.add(1, "y");
+        let version = set.version();
 
+        assert_eq!(set.modify_bucket(1, |items| items.len()), Some(2));
         assert!(
-            set.lowest_score().is_none(),
-            "Should be None for an empty set"
+            set.items_since(version).is_empty(),
+            "Reading marks nothing as new"
+        );
+
+        set.modify_bucket(1, |items| {
+            items.remove(0);
+            items.push("z");
+            items.push("w");
+        });
+        assert_eq!(
+            set.items_since(version),
+            vec![(1, "z"), (1, "w")],
+            "Pushed items are new even where a removed item used to be"
         );
+        assert_eq!(set.len(), 3);
+
+        let version = set.version();
+        set.modify_bucket(1, |items| items.sort());
         assert!(
-            set.highest_score().is_none(),
-            "Should be None for an empty set"
+            set.items_since(version).is_empty(),
+            "Records follow items when reordered"
         );
+        set.modify_bucket(1, |items| {
+            items.pop();
+            items.push("v");
+        });
+        assert_eq!(set.items_since(version), vec![(1, "v")]);
     }
 
     #[test]
-    fn lowest_and_highest_score_single_item() {
-        let set = ScoredSortedSet::new();
-        set.add(10, "Alice".to_string());
+    fn modify_bucket_panic_keeps_items_and_counts() {
+        let set = std::sync::Arc::new(ScoredSortedSet::with_cached_aggregates());
+        set.add(10, "Alice");
+        set.add(10, "Bob");
 
-        let lowest = set.lowest_score().unwrap();
-        assert_eq!(lowest.0, 10, "Lowest score should be 10");
-        assert_eq!(
-            lowest.1,
-            vec!["Alice".to_string()],
-            "Lowest score item should be Alice"
-        );
+        let panicking = std::sync::Arc::clone(&set);
+        let result = std::thread::spawn(move || {
+            panicking.modify_bucket(10, |items| {
+                items.push("Charlie");
+                panic!("boom");
+            });
+        })
+        .join();
+        assert!(result.is_err());
 
-        let highest = set.highest_score().unwrap();
-        assert_eq!(highest.0, 10, "Highest score should also be 10");
-        assert_eq!(
-            highest.1,
-            vec!["Alice".to_string()],
-            "Highest score item should also be Alice"
-        );
+        // Without parking_lot the lock is poisoned, but try_read still reads it
+        let inner = crate::lock::try_read(&set.inner).unwrap();
+        assert_eq!(inner[&10], vec!["Alice", "Bob", "Charlie"]);
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.sum_of_scores(), 30);
     }
 
     #[test]
-    fn lowest_and_highest_score_multiple_items() {
-        let set = ScoredSortedSet::new();
-        set.add(10, "Alice".to_string());
-        set.add(20, "Bob".to_string());
-        set.add(30, "Charlie".to_string());
+    fn modify_bucket_unique_violation_leaves_set_unchanged() {
+        let set = ScoredSortedSet::with_unique_scores();
+        set.add(10, "Alice");
 
-        let lowest = set.lowest_score().unwrap();
-        assert_eq!(lowest.0, 10, "Lowest score should be 10");
-        assert_eq!(
-            lowest.1,
-            vec!["Alice".to_string()],
-            "Lowest score item should be Alice"
-        );
+        let version = set.version();
+
+        assert_eq!(set.modify_bucket(10, |items| items.push("Bob")), None);
+        assert_eq!(set.get(10).unwrap(), vec!["Alice"], "The set is unchanged");
+        assert_eq!(set.len(), 1);
+        assert_eq!(set.version(), version);
+        assert!(!set.add(10, "Charlie"), "Score 10 is still taken");
 
-        let highest = set.highest_score().unwrap();
-        assert_eq!(highest.0, 30, "Highest score should be 30");
         assert_eq!(
-            highest.1,
-            vec!["Charlie".to_string()],
-            "Highest score item should be Charlie"
+            set.modify_bucket(10, |items| {
+                items[0] = "Dave";
+                items.len()
+            }),
+            Some(1)
         );
+        assert_eq!(set.get(10).unwrap(), vec!["Dave"]);
     }
 
     #[test]
-    fn updating_scores_affects_lowest_and_highest_correctly() {
+    fn raw_guards_read_and_write_the_map() {
         let set = ScoredSortedSet::new();
-        set.add(10, "Alice".to_string());
-        set.add(20, "Bob".to_string());
+        set.add(10, "Alice");
 
-        set.update_score(10, 5, &"Alice".to_string()); // Update Alice to a lower score
+        set.raw_write().get_mut(&10).unwrap().push("Bob");
+        let inner = set.raw_read();
+        assert_eq!(inner[&10], vec!["Alice", "Bob"]);
+        assert_eq!(inner.len(), 1);
+    }
 
-        let lowest = set.lowest_score().unwrap();
-        assert_eq!(lowest.0, 5, "After update, lowest score should be 5");
-        assert_eq!(
-            lowest.1,
-            vec!["Alice".to_string()],
-            "Lowest score item should now be Alice"
+    #[test]
+    fn reservations_are_allocated_on_first_use() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice");
+        assert!(
+            set.reserved.get().is_none(),
+            "Adding alone reserves nothing"
         );
 
-        let highest = set.highest_score().unwrap();
-        assert_eq!(highest.0, 20, "Highest score remains 20");
-        assert_eq!(
-            highest.1,
-            vec!["Bob".to_string()],
-            "Highest score item remains Bob"
-        );
+        set.prepare_scores(&[(20, 32)]);
+        assert_eq!(set.reservations().unwrap().get(&20), Some(&32));
     }
 
     #[test]
-    fn all_scores_empty_set() {
-        let set = ScoredSortedSet::<String>::new();
-        let scores = set.all_scores();
-        assert!(scores.is_empty(), "Expected no scores for an empty set");
+    fn drain_releases_reservations() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice");
+        set.prepare_scores(&[(20, 32)]);
+
+        assert_eq!(set.drain(), vec![(10, "Alice")]);
+        assert!(set.reservations().unwrap().is_empty());
+        set.add(20, "Bob");
+        assert!(
+            set.raw_read()[&20].capacity() < 32,
+            "The reservation is gone"
+        );
     }
 
     #[test]
-    fn all_scores_non_empty_set() {
+    fn adjust_scores_where_zero_delta_is_a_no_op() {
         let set = ScoredSortedSet::new();
-        set.add(10, "Alice".to_string());
-        set.add(20, "Bob".to_string());
-        set.add(30, "Charlie".to_string());
+        set.add(1, "a");
+        set.add(1, "b");
+        set.add(1, "c");
+        let version = set.version();
 
-        let scores = set.all_scores();
-        assert_eq!(scores.len(), 3, "Expected three scores in the set");
+        assert_eq!(set.adjust_scores_where(|&item| item == "a", 0), 0);
         assert_eq!(
-            scores,
-            vec![10, 20, 30],
-            "Scores should be in ascending order"
+            set.get(1).unwrap(),
+            vec!["a", "b", "c"],
+            "Order is unchanged"
         );
+        assert_eq!(set.version(), version);
     }
 
-    // This tests the unique nature of scores implicitly
     #[test]
-    fn all_scores_with_duplicate_scores() {
+    fn union_into_unique_set_keeps_one_item_per_score() {
+        let unique = ScoredSortedSet::with_unique_scores();
+        unique.add(1, "a");
+        let other = ScoredSortedSet::new();
+        other.add(1, "x");
+        other.add(5, "b");
+        other.add(5, "c");
+
+        let merged = unique.union(&other);
+        assert_eq!(
+            merged.get(1).unwrap(),
+            vec!["a"],
+            "Score 1 is already taken"
+        );
+        assert_eq!(
+            merged.get(5).unwrap(),
+            vec!["b"],
+            "Only the first item is kept"
+        );
+        assert_eq!(merged.len(), 2);
+        assert!(!merged.add(5, "d"));
+    }
+
+    #[cfg(not(feature = "parking_lot"))]
+    #[test]
+    fn poisoned_insertion_records_are_still_used() {
+        let set = std::sync::Arc::new(ScoredSortedSet::with_insertion_tracking());
+        set.add(10, "Alice");
+        let version = set.version();
+
+        let panicking = std::sync::Arc::clone(&set);
+        let result = std::thread::spawn(move || {
+            let _stamps = panicking.stamps.as_ref().unwrap().lock().unwrap();
+            panic!("boom");
+        })
+        .join();
+        assert!(result.is_err());
+
+        assert!(set.add(20, "Bob"));
+        assert_eq!(set.items_since(version), vec![(20, "Bob")]);
+    }
+
+    #[test]
+    fn percentile_is_not_pushed_up_by_rounding_error() {
         let set = ScoredSortedSet::new();
-        set.add(10, "Alice".to_string());
-        set.add(10, "Duplicate Alice".to_string()); // Duplicate score
-        set.add(20, "Bob".to_string());
+        for score in 1..=100 {
+            set.add(score, score);
+        }
 
-        let scores = set.all_scores();
-        assert_eq!(scores.len(), 2, "Expected scores to be unique");
         assert_eq!(
-            scores,
-            vec![10, 20],
-            "Scores should be in ascending order and unique"
+            set.percentile(0.07),
+            Some(7),
+            "0.07 * 100.0 is slightly above 7"
+        );
+        assert_eq!(set.percentile(0.14), Some(14));
+        assert_eq!(
+            set.percentile(0.071),
+            Some(8),
+            "Real fractions still round up"
         );
+        for percent in 1..=100 {
+            assert_eq!(set.percentile(percent as f64 / 100.0), Some(percent));
+        }
     }
 }
@@ -0,0 +1,74 @@
+//! The reader-writer lock guarding each set's contents.
+//!
+//! By default this is `std::sync::RwLock`, and a panic while the lock is held poisons it, so
+//! every later call on the set panics too. With the `parking_lot` feature it is
+//! `parking_lot::RwLock` instead, which does not poison and is faster under contention.
+//! The helpers here hide the difference so method bodies read the same either way.
+
+#[cfg(feature = "parking_lot")]
+pub use parking_lot::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+#[cfg(not(feature = "parking_lot"))]
+pub use std::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Acquires `lock` for reading.
+///
+/// # Panics
+///
+/// Without the `parking_lot` feature, panics if the lock is poisoned.
+pub fn read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    #[cfg(feature = "parking_lot")]
+    return lock.read();
+    #[cfg(not(feature = "parking_lot"))]
+    return lock.read().unwrap();
+}
+
+/// Acquires `lock` for writing.
+///
+/// # Panics
+///
+/// Without the `parking_lot` feature, panics if the lock is poisoned.
+pub fn write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    #[cfg(feature = "parking_lot")]
+    return lock.write();
+    #[cfg(not(feature = "parking_lot"))]
+    return lock.write().unwrap();
+}
+
+/// Consumes `lock` and returns the value it guards.
+///
+/// # Panics
+///
+/// Without the `parking_lot` feature, panics if the lock is poisoned.
+pub fn into_inner<T>(lock: RwLock<T>) -> T {
+    #[cfg(feature = "parking_lot")]
+    return lock.into_inner();
+    #[cfg(not(feature = "parking_lot"))]
+    return lock.into_inner().unwrap();
+}
+
+/// Acquires `lock` for reading if that can be done without blocking, or returns `None`.
+/// Unlike `read`, a poisoned lock is still read rather than panicking, so diagnostics keep
+/// working after a panic.
+pub fn try_read<T>(lock: &RwLock<T>) -> Option<RwLockReadGuard<'_, T>> {
+    #[cfg(feature = "parking_lot")]
+    return lock.try_read();
+    #[cfg(not(feature = "parking_lot"))]
+    return match lock.try_read() {
+        Ok(guard) => Some(guard),
+        Err(std::sync::TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+        Err(std::sync::TryLockError::WouldBlock) => None,
+    };
+}
+
+/// Locks `mutex`, which guards one of a set's side tables.
+/// Unlike `read` and `write`, a poisoned mutex is still used rather than panicking: side
+/// tables are only changed with the set's write lock held, so a panic midway through a change
+/// also poisons that lock, which reports it.
+pub fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    #[cfg(feature = "parking_lot")]
+    return mutex.lock();
+    #[cfg(not(feature = "parking_lot"))]
+    return mutex
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+}
@@ -0,0 +1,70 @@
+use rand::Rng;
+
+use crate::{item_at, lock, ScoredSortedSet};
+
+impl<T, S: Ord + Copy> ScoredSortedSet<T, S> {
+    /// Returns a uniformly random item with its score, using the thread-local random number
+    /// generator. Every item is equally likely, so scores holding more items are picked
+    /// proportionally more often. Returns `None` if the set is empty.
+    pub fn random_member(&self) -> Option<(S, T)>
+    where
+        T: Clone,
+    {
+        self.random_member_with(&mut rand::rng())
+    }
+
+    /// Returns a uniformly random item with its score like `random_member`, drawing from `rng`,
+    /// for example a seeded generator for reproducible picks.
+    pub fn random_member_with<R>(&self, rng: &mut R) -> Option<(S, T)>
+    where
+        T: Clone,
+        R: Rng + ?Sized,
+    {
+        let inner = lock::read(&self.inner);
+        let total: usize = inner.values().map(Vec::len).sum();
+        if total == 0 {
+            return None;
+        }
+        item_at(&inner, rng.random_range(0..total))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::ScoredSortedSet;
+
+    #[test]
+    fn random_member_weights_by_bucket_size() {
+        let set = ScoredSortedSet::new();
+        set.add(1, "Alice".to_string());
+        for i in 0..9 {
+            set.add(2, i.to_string());
+        }
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let draws = 10_000;
+        let from_one = (0..draws)
+            .filter(|_| set.random_member_with(&mut rng).unwrap().0 == 1)
+            .count();
+        // One item in ten is at score 1, so expect about 1,000 draws; picking a score first
+        // would give about 5,000.
+        assert!(
+            (800..1200).contains(&from_one),
+            "Score 1 drawn {} times out of {}",
+            from_one,
+            draws
+        );
+    }
+
+    #[test]
+    fn random_member_of_empty_set() {
+        let set: ScoredSortedSet<&str> = ScoredSortedSet::new();
+        assert_eq!(set.random_member(), None);
+
+        set.add(5, "Alice");
+        assert_eq!(set.random_member(), Some((5, "Alice")));
+    }
+}
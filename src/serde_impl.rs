@@ -0,0 +1,204 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+use crate::{lock, ScoredSortedSet};
+
+/// Version of the serialized format, written alongside the data so that loading data written
+/// in a different format fails with a clear error instead of misparsing.
+const FORMAT_VERSION: u32 = 1;
+
+const FIELDS: &[&str] = &["version", "data"];
+
+/// Serializes the set as `{"version": 1, "data": {score: [items...]}}`, with scores in
+/// ascending order and items in insertion order. The set is read-locked while serializing.
+impl<T: Serialize, K: Serialize> Serialize for ScoredSortedSet<T, K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let inner = lock::read(&self.inner);
+        let mut state = serializer.serialize_struct("ScoredSortedSet", 2)?;
+        state.serialize_field("version", &FORMAT_VERSION)?;
+        state.serialize_field("data", &*inner)?;
+        state.end()
+    }
+}
+
+/// Deserializes a set written by the `Serialize` implementation.
+/// Fails with an error naming both versions if the data was written in a different format
+/// version. Scores with no items are skipped. The resulting set has no score bounds or other
+/// options enabled.
+impl<'de, T, K> Deserialize<'de> for ScoredSortedSet<T, K>
+where
+    T: Deserialize<'de>,
+    K: Deserialize<'de> + Ord + Copy,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct("ScoredSortedSet", FIELDS, SetVisitor(PhantomData))
+    }
+}
+
+struct SetVisitor<T, K>(PhantomData<(T, K)>);
+
+/// Rejects any format version other than the current one.
+fn check_version<E: de::Error>(version: u32) -> Result<(), E> {
+    if version != FORMAT_VERSION {
+        return Err(E::custom(format_args!(
+            "unsupported ScoredSortedSet format version {}, expected {}",
+            version, FORMAT_VERSION
+        )));
+    }
+    Ok(())
+}
+
+/// Builds a set from deserialized data, dropping scores with no items.
+fn build<T, K: Ord + Copy>(mut data: BTreeMap<K, Vec<T>>) -> ScoredSortedSet<T, K> {
+    data.retain(|_, items| !items.is_empty());
+    ScoredSortedSet::with_score_type().new_like(data)
+}
+
+impl<'de, T, K> Visitor<'de> for SetVisitor<T, K>
+where
+    T: Deserialize<'de>,
+    K: Deserialize<'de> + Ord + Copy,
+{
+    type Value = ScoredSortedSet<T, K>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a versioned ScoredSortedSet")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let version: u32 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        check_version(version)?; // Checked before the data is parsed
+        let data = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        Ok(build(data))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut version = None;
+        let mut data = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "version" => {
+                    if version.is_some() {
+                        return Err(de::Error::duplicate_field("version"));
+                    }
+                    let value: u32 = map.next_value()?;
+                    check_version(value)?; // Fail fast when the version comes first
+                    version = Some(value);
+                }
+                "data" => {
+                    if data.is_some() {
+                        return Err(de::Error::duplicate_field("data"));
+                    }
+                    data = Some(map.next_value()?);
+                }
+                _ => return Err(de::Error::unknown_field(&key, FIELDS)),
+            }
+        }
+        version.ok_or_else(|| de::Error::missing_field("version"))?;
+        let data = data.ok_or_else(|| de::Error::missing_field("data"))?;
+        Ok(build(data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ScoredSortedSet;
+
+    #[test]
+    fn serializes_with_version_envelope() {
+        let set = ScoredSortedSet::new();
+        set.add(20, "Bob".to_string());
+        set.add(10, "Alice".to_string());
+
+        let json = serde_json::to_string(&set).unwrap();
+        assert_eq!(
+            json,
+            r#"{"version":1,"data":{"10":["Alice"],"20":["Bob"]}}"#
+        );
+    }
+
+    #[test]
+    fn round_trip_preserves_contents() {
+        let set = ScoredSortedSet::new();
+        set.add(10, "Alice".to_string());
+        set.add(10, "Bob".to_string());
+        set.add(-5, "Charlie".to_string());
+
+        let json = serde_json::to_string(&set).unwrap();
+        let loaded: ScoredSortedSet<String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.all_scores(), vec![-5, 10]);
+        assert_eq!(
+            loaded.get(10).unwrap(),
+            vec!["Alice".to_string(), "Bob".to_string()]
+        );
+        assert_eq!(loaded.len(), 3);
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let json = r#"{"version":2,"data":{"10":["Alice"]}}"#;
+        let err = serde_json::from_str::<ScoredSortedSet<String>>(json)
+            .err()
+            .unwrap();
+        assert!(
+            err.to_string()
+                .contains("unsupported ScoredSortedSet format version 2, expected 1"),
+            "Unexpected error: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn rejects_missing_version() {
+        let json = r#"{"data":{"10":["Alice"]}}"#;
+        let err = serde_json::from_str::<ScoredSortedSet<String>>(json)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("missing field `version`"));
+    }
+
+    #[test]
+    fn round_trip_preserves_order_exactly() {
+        let set = ScoredSortedSet::new();
+        for (score, id) in [(3, 1), (-1, 2), (3, 3), (0, 4), (-1, 5), (3, 6)] {
+            set.add(score, id);
+        }
+
+        let json = serde_json::to_string(&set).unwrap();
+        let loaded: ScoredSortedSet<u32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            loaded.iter().collect::<Vec<_>>(),
+            set.iter().collect::<Vec<_>>(),
+            "Scores and items within each score should keep their order"
+        );
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), json);
+    }
+
+    #[test]
+    fn skips_empty_scores() {
+        let json = r#"{"version":1,"data":{"10":[],"20":["Bob"]}}"#;
+        let loaded: ScoredSortedSet<String> = serde_json::from_str(json).unwrap();
+        assert_eq!(loaded.all_scores(), vec![20]);
+        assert_eq!(loaded.len(), 1);
+    }
+
+    #[test]
+    fn round_trip_with_i64_scores() {
+        let set = ScoredSortedSet::<String, i64>::with_score_type();
+        set.add(i64::MAX, "Alice".to_string());
+        set.add(-1, "Bob".to_string());
+
+        let json = serde_json::to_string(&set).unwrap();
+        let loaded: ScoredSortedSet<String, i64> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.all_scores(), vec![-1, i64::MAX]);
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), json);
+    }
+}
@@ -0,0 +1,199 @@
+use std::cmp::Reverse;
+
+use crate::ScoredSortedSet;
+
+/// A scored, sorted set split into several independently locked `ScoredSortedSet` shards, so
+/// that writes to different scores can proceed in parallel instead of queuing on one lock.
+/// Each score lives in shard `score.rem_euclid(shard_count)`, so all items sharing a score
+/// stay together and per-score operations touch a single shard.
+///
+/// Queries spanning all scores, such as `highest_scores`, read the shards one after another
+/// and merge the results, so they are not a consistent snapshot of concurrent writes.
+pub struct ShardedScoredSet<T> {
+    shards: Vec<ScoredSortedSet<T>>,
+}
+
+impl<T> ShardedScoredSet<T> {
+    /// Creates a new, empty `ShardedScoredSet` with `shard_count` shards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shard_count` is zero.
+    pub fn with_shards(shard_count: usize) -> Self {
+        assert!(shard_count > 0, "shard_count must be positive");
+        ShardedScoredSet {
+            shards: (0..shard_count).map(|_| ScoredSortedSet::new()).collect(),
+        }
+    }
+
+    /// Returns the number of shards.
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Returns the shard holding `score`.
+    fn shard(&self, score: i32) -> &ScoredSortedSet<T> {
+        let index = (score as i64).rem_euclid(self.shards.len() as i64);
+        &self.shards[index as usize]
+    }
+
+    /// Adds an item with a given score, locking only the score's shard.
+    /// Returns `true` if the item was added, which is always the case because shards have no
+    /// score bounds and do not require unique scores.
+    pub fn add(&self, score: i32, item: T) -> bool {
+        self.shard(score).add(score, item)
+    }
+
+    /// Removes a specified item from the set for a given score, locking only the score's shard.
+    /// Returns `true` if the item was removed.
+    pub fn remove(&self, score: i32, item: &T) -> bool
+    where
+        T: PartialEq + Clone,
+    {
+        self.shard(score).remove(score, item)
+    }
+
+    /// Moves a specified item from `old_score` to `new_score`, returning `true` if it was found.
+    /// When the scores live in different shards, the item is removed from one shard before it
+    /// is added to the other, so concurrent readers may briefly see it in neither.
+    pub fn update_score(&self, old_score: i32, new_score: i32, item: &T) -> bool
+    where
+        T: PartialEq + Clone,
+    {
+        let old_shard = self.shard(old_score);
+        let new_shard = self.shard(new_score);
+        if std::ptr::eq(old_shard, new_shard) {
+            return old_shard.update_score(old_score, new_score, item);
+        }
+        match old_shard.remove_if_score(item, old_score) {
+            true => new_shard.add(new_score, item.clone()),
+            false => false,
+        }
+    }
+
+    /// Retrieves a clone of the items associated with a given score.
+    /// Returns `None` if the score does not exist in the set.
+    pub fn get(&self, score: i32) -> Option<Vec<T>>
+    where
+        T: Clone,
+    {
+        self.shard(score).get(score)
+    }
+
+    /// Returns the total number of items across all shards.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(ScoredSortedSet::len).sum()
+    }
+
+    /// Returns `true` if no shard holds any items.
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(ScoredSortedSet::is_empty)
+    }
+
+    /// Returns all populated scores in ascending order, merged across shards.
+    pub fn all_scores(&self) -> Vec<i32> {
+        let mut scores: Vec<i32> = self
+            .shards
+            .iter()
+            .flat_map(ScoredSortedSet::all_scores)
+            .collect();
+        scores.sort_unstable(); // No score lives in two shards, so there are no duplicates
+        scores
+    }
+
+    /// Returns the `n` highest scores and their items, in descending order of score, merged
+    /// from the `n` highest scores of each shard.
+    pub fn highest_scores(&self, n: usize) -> Vec<(i32, Vec<T>)>
+    where
+        T: Clone,
+    {
+        let mut merged: Vec<(i32, Vec<T>)> = self
+            .shards
+            .iter()
+            .flat_map(|shard| shard.highest_scores(n))
+            .collect();
+        merged.sort_unstable_by_key(|&(score, _)| Reverse(score));
+        merged.truncate(n);
+        merged
+    }
+
+    /// Returns the `n` lowest scores and their items, in ascending order of score, merged from
+    /// the `n` lowest scores of each shard.
+    pub fn lowest_scores(&self, n: usize) -> Vec<(i32, Vec<T>)>
+    where
+        T: Clone,
+    {
+        let mut merged: Vec<(i32, Vec<T>)> = self
+            .shards
+            .iter()
+            .flat_map(|shard| shard.lowest_scores(n))
+            .collect();
+        merged.sort_unstable_by_key(|&(score, _)| score);
+        merged.truncate(n);
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShardedScoredSet;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn negative_scores_map_to_valid_shards() {
+        let set = ShardedScoredSet::with_shards(3);
+        set.add(-7, "Alice");
+        set.add(i32::MIN, "Bob");
+        set.add(i32::MAX, "Charlie");
+
+        assert_eq!(set.get(-7).unwrap(), vec!["Alice"]);
+        assert_eq!(set.all_scores(), vec![i32::MIN, -7, i32::MAX]);
+        assert_eq!(set.len(), 3);
+    }
+
+    #[test]
+    fn update_score_across_shards() {
+        let set = ShardedScoredSet::with_shards(4);
+        set.add(1, "Alice");
+        set.add(1, "Bob");
+
+        assert!(set.update_score(1, 2, &"Alice"), "Moves to another shard");
+        assert!(set.update_score(2, 6, &"Alice"), "Stays in the same shard");
+        assert!(!set.update_score(2, 3, &"Alice"), "No longer at score 2");
+        assert_eq!(set.get(1).unwrap(), vec!["Bob"]);
+        assert_eq!(set.get(6).unwrap(), vec!["Alice"]);
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn concurrent_adds_merge_in_order() {
+        let set = Arc::new(ShardedScoredSet::with_shards(8));
+        let handles: Vec<_> = (0..8)
+            .map(|t| {
+                let set = Arc::clone(&set);
+                thread::spawn(move || {
+                    for i in 0..500 {
+                        let score = (i * 8 + t) - 2000;
+                        set.add(score, score);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(set.len(), 4000);
+        assert_eq!(set.all_scores(), (-2000..2000).collect::<Vec<_>>());
+
+        let top = set.highest_scores(3);
+        assert_eq!(
+            top,
+            vec![(1999, vec![1999]), (1998, vec![1998]), (1997, vec![1997])]
+        );
+        let bottom: Vec<i32> = set.lowest_scores(3).into_iter().map(|(s, _)| s).collect();
+        assert_eq!(bottom, vec![-2000, -1999, -1998]);
+        assert!(set.highest_scores(0).is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use crate::{lock, ScoredSortedSet};
+
+/// A scored, sorted set that stores each item behind an `Arc`, so reads hand out cheap
+/// reference-counted clones instead of deep copies of the items.
+///
+/// This suits large items that are read far more often than they are written. The tradeoff
+/// is an extra allocation per item on insert, a pointer indirection on every comparison, and
+/// that returned items are shared and immutable: they stay alive while any reader holds them,
+/// even after they are removed from the set.
+pub struct SharedScoredSet<T> {
+    set: ScoredSortedSet<Arc<T>>,
+}
+
+impl<T> Default for SharedScoredSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> SharedScoredSet<T> {
+    /// Creates a new, empty `SharedScoredSet`.
+    pub fn new() -> Self {
+        SharedScoredSet {
+            set: ScoredSortedSet::new(),
+        }
+    }
+
+    /// Adds an item with a given score, wrapping it in an `Arc`.
+    /// Returns `true` if the item was added, which is always the case because the wrapped set
+    /// has no score bounds and does not require unique scores.
+    pub fn add(&self, score: i32, item: T) -> bool {
+        self.set.add(score, Arc::new(item))
+    }
+
+    /// Adds an already shared item with a given score, without copying it.
+    pub fn add_shared(&self, score: i32, item: Arc<T>) -> bool {
+        self.set.add(score, item)
+    }
+
+    /// Removes every item equal to `item` from a given score.
+    /// Returns `true` if anything was removed.
+    pub fn remove(&self, score: i32, item: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        let mut inner = lock::write(&self.set.inner);
+        let removed = self
+            .set
+            .extract_locked(&mut inner, score, |shared| **shared == *item);
+        !removed.is_empty()
+    }
+
+    /// Returns the items for a given score as shared handles, without copying the items.
+    /// Returns `None` if the score does not exist in the set.
+    pub fn get_shared(&self, score: i32) -> Option<Vec<Arc<T>>> {
+        self.set.get(score)
+    }
+
+    /// Returns the `n` highest scores and their items as shared handles, in descending order
+    /// of score, without copying the items.
+    pub fn highest_scores_shared(&self, n: usize) -> Vec<(i32, Vec<Arc<T>>)> {
+        self.set.highest_scores(n)
+    }
+
+    /// Returns the underlying set of shared items, for the rest of the `ScoredSortedSet` API.
+    /// Its read methods clone only the `Arc`s.
+    pub fn as_set(&self) -> &ScoredSortedSet<Arc<T>> {
+        &self.set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::SharedScoredSet;
+
+    #[test]
+    fn reads_share_one_allocation() {
+        let set = SharedScoredSet::new();
+        set.add(10, vec![0u8; 1024]);
+
+        let first = set.get_shared(10).unwrap();
+        let second = set.get_shared(10).unwrap();
+        assert!(Arc::ptr_eq(&first[0], &second[0]), "No deep copy was made");
+
+        let top = set.highest_scores_shared(1);
+        assert!(Arc::ptr_eq(&top[0].1[0], &first[0]));
+        assert_eq!(Arc::strong_count(&first[0]), 4);
+    }
+
+    #[test]
+    fn removed_items_outlive_the_set_entry() {
+        let set = SharedScoredSet::new();
+        let item = Arc::new("Alice".to_string());
+        set.add_shared(10, Arc::clone(&item));
+        set.add(10, "Bob".to_string());
+
+        let held = set.get_shared(10).unwrap();
+        assert!(set.remove(10, &"Alice".to_string()));
+        assert!(!set.remove(10, &"Alice".to_string()));
+        assert_eq!(*held[0], "Alice", "Readers keep their handle");
+        assert_eq!(set.as_set().len(), 1);
+        assert!(Arc::ptr_eq(&held[0], &item));
+    }
+}